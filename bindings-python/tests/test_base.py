@@ -6,7 +6,7 @@ test_csv_path = '/home/shoe/PSI/datasets/data/PUMS_california_demographics_1000/
 def test_basic_path():
     print('file path test')
 
-    with yarrow.Analysis() as analysis:
+    with yarrow.Analysis(epsilon_budget=1.) as analysis:
         PUMS = yarrow.Dataset('PUMS', test_csv_path)
 
         age = PUMS[('age', int)]
@@ -55,7 +55,8 @@ def test_basic_path():
             maximum_y=1
         )
 
-    print('analysis is valid:', analysis.validate())
+    print('analysis is valid:', analysis.validate().valid)
+    print('diagnostics:', analysis.diagnose())
 
     print('epsilon:', analysis.epsilon)
 
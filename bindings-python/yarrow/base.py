@@ -133,7 +133,7 @@ def _to_component(value):
         'Literal', options={'value': array_nd(value)})
 
 
-def dp_mean(data, epsilon, minimum, maximum, num_records):
+def dp_mean(data, epsilon, minimum, maximum, num_records, mechanism='LAPLACE', delta=0.):
     return Component('DPMean', {
         'data': _to_component(data),
         'num_records': _to_component(num_records),
@@ -141,11 +141,12 @@ def dp_mean(data, epsilon, minimum, maximum, num_records):
         'maximum': _to_component(maximum)
     }, {
          'epsilon': epsilon,
-         'mechanism': types_pb2.Mechanism.Value("LAPLACE")
+         'mechanism': types_pb2.Mechanism.Value(mechanism),
+         'delta': delta
      })
 
 
-def dp_variance(data, epsilon, minimum, maximum, num_records):
+def dp_variance(data, epsilon, minimum, maximum, num_records, mechanism='LAPLACE', delta=0.):
     return Component('DPVariance', {
         'data': _to_component(data),
         'num_records': _to_component(num_records),
@@ -153,11 +154,12 @@ def dp_variance(data, epsilon, minimum, maximum, num_records):
         'maximum': _to_component(maximum)
     }, {
          'epsilon': epsilon,
-         'mechanism': types_pb2.Mechanism.Value("LAPLACE")
+         'mechanism': types_pb2.Mechanism.Value(mechanism),
+         'delta': delta
      })
 
 
-def dp_covariance(data_x, data_y, epsilon, num_records, minimum_x, maximum_x, minimum_y, maximum_y):
+def dp_covariance(data_x, data_y, epsilon, num_records, minimum_x, maximum_x, minimum_y, maximum_y, mechanism='LAPLACE', delta=0.):
     return Component('DPCovariance', {
         'data_x': _to_component(data_x),
         'data_y': _to_component(data_y),
@@ -168,11 +170,12 @@ def dp_covariance(data_x, data_y, epsilon, num_records, minimum_x, maximum_x, mi
         'maximum_y': _to_component(maximum_y)
     }, {
         'epsilon': epsilon,
-        'mechanism': types_pb2.Mechanism.Value("LAPLACE")
+        'mechanism': types_pb2.Mechanism.Value(mechanism),
+        'delta': delta
     })
 
 
-def dp_moment_raw(data, epsilon, minimum, maximum, num_records, order):
+def dp_moment_raw(data, epsilon, minimum, maximum, num_records, order, mechanism='LAPLACE', delta=0.):
     return Component('DPMomentRaw', {
         'data': _to_component(data),
         'num_records': _to_component(num_records),
@@ -180,20 +183,54 @@ def dp_moment_raw(data, epsilon, minimum, maximum, num_records, order):
         'maximum': _to_component(maximum)
     }, {
         'epsilon': epsilon,
-        'mechanism': types_pb2.Mechanism.Value("LAPLACE"),
+        'mechanism': types_pb2.Mechanism.Value(mechanism),
+        'delta': delta,
         'order': order
      })
 
 
+def exponential_mechanism(data, candidates, epsilon, utility='COUNT'):
+    """select one of the public candidates, where utility is COUNT for the most common or MEDIAN for the median"""
+    return Component('ExponentialMechanism', {
+        'data': _to_component(data),
+        'candidates': _to_component(candidates)
+    }, {
+        'epsilon': epsilon,
+        'utility': analysis_pb2.ExponentialMechanism.Utility.Value(utility)
+    })
+
+
+def custom(name, **arguments):
+    """evaluated by the component registered under the same name"""
+    return Component('Custom', {key: _to_component(value) for key, value in arguments.items()}, {'name': name})
+
+
+def register_component(name, evaluate):
+    """evaluate custom components with this name by calling evaluate, which maps a ReleaseNode of arguments to a ReleaseNode of outputs"""
+    core_wrapper.register_component(name, evaluate)
+
+
 class Analysis(object):
-    def __init__(self, *components, datasets=None, distance='APPROXIMATE', neighboring='SUBSTITUTE'):
+    def __init__(self, *components, datasets=None, distance='APPROXIMATE', neighboring='SUBSTITUTE',
+                 epsilon_budget=None, delta_budget=None, rho_budget=None, composition='BASIC',
+                 group_size=1):
         self.components: list = list(components)
         self.datasets: list = datasets or []
         self.release_proto: release_pb2.Release = None
         self.distance: str = distance
         self.neighboring: str = neighboring
+        # upper bounds on the privacy usage of the analysis
+        self.epsilon_budget: float = epsilon_budget
+        self.delta_budget: float = delta_budget
+        self.rho_budget: float = rho_budget
+        # composition theorem for APPROXIMATE analyses, one of BASIC, ADVANCED or OPTIMAL
+        self.composition: str = composition
+        # number of records protected together, such as the members of a household
+        self.group_size: int = group_size
 
         self._context_cache = None
+        # node ids assigned to components in the most recently built analysis proto
+        self._components_by_id: dict = {}
 
     def _make_analysis_proto(self):
 
@@ -217,6 +254,8 @@ class Analysis(object):
         for component in self.components:
             enqueue(component)
 
+        self._components_by_id = {node_id: component for component, node_id in discovered_components.items()}
+
         vertices = {}
 
         while not component_queue.empty():
@@ -239,8 +278,13 @@ class Analysis(object):
         return analysis_pb2.Analysis(
             graph=vertices,
             privacy_definition=analysis_pb2.PrivacyDefinition(
+                epsilon=self.epsilon_budget or 0.,
+                delta=self.delta_budget or 0.,
+                rho=self.rho_budget or 0.,
+                group_size=self.group_size,
                 distance=analysis_pb2.PrivacyDefinition.Distance.Value(self.distance),
-                neighboring=analysis_pb2.PrivacyDefinition.Neighboring.Value(self.neighboring)
+                neighboring=analysis_pb2.PrivacyDefinition.Neighboring.Value(self.neighboring),
+                composition=types_pb2.Composition.Value(self.composition)
             )
         )
 
@@ -259,14 +303,67 @@ class Analysis(object):
         return core_wrapper.validate_analysis(
             self._make_analysis_proto())
 
+    def diagnose(self):
+        """pair each validator diagnostic with the component that caused it"""
+        validated = self.validate()
+        return [{
+            'component': self._components_by_id.get(diagnostic.node_id),
+            'severity': release_pb2.Diagnostic.Severity.Name(diagnostic.severity),
+            'argument': diagnostic.argument,
+            'code': diagnostic.code,
+            'message': diagnostic.message
+        } for diagnostic in validated.diagnostics]
+
     @property
-    def epsilon(self):
-        return core_wrapper.compute_epsilon(
+    def privacy_usage(self):
+        return core_wrapper.compute_privacy(
             self._make_analysis_proto(),
             self._make_release_proto())
 
+    @property
+    def epsilon(self):
+        return self.privacy_usage.total.epsilon
+
+    def accuracies(self, alpha=.05):
+        """half-width of the (1 - alpha) interval of the noise added by each privatizing component"""
+        accuracies = core_wrapper.to_accuracy(
+            self._make_analysis_proto(),
+            self._make_release_proto(),
+            alpha)
+        return {self._components_by_id.get(node_id): accuracy.value
+                for node_id, accuracy in accuracies.values.items()}
+
+    def epsilons_for(self, accuracies):
+        """smallest epsilon meeting each target, where accuracies maps components to (half-width, alpha),
+        e.g. {mean: (2., .05)} for +-2 with 95% confidence"""
+        analysis_proto = self._make_analysis_proto()
+        node_ids = {component: node_id for node_id, component in self._components_by_id.items()}
+        privacy_usage = core_wrapper.from_accuracy(
+            analysis_proto,
+            self._make_release_proto(),
+            release_pb2.Accuracies(values={
+                node_ids[component]: release_pb2.Accuracy(value=value, alpha=alpha)
+                for component, (value, alpha) in accuracies.items()
+            }))
+
+        # the validator leaves out nodes whose epsilon cannot be derived from the target
+        underived = [node_ids[component] for component in accuracies if node_ids[component] not in privacy_usage.values]
+        if underived:
+            raise ValueError("no epsilon meets the target accuracy of " + ", ".join(
+                f"node {node_id} ({self._components_by_id[node_id].name})" for node_id in sorted(underived))
+                + "; the mechanism may not support accuracies, the target may be infeasible, or the bounds may be unknown")
+        return {self._components_by_id.get(node_id): usage.epsilon
+                for node_id, usage in privacy_usage.values.items()}
+
     def release(self):
         analysis_proto: analysis_pb2.Analysis = self._make_analysis_proto()
+
+        # the runtime refuses invalid analyses, so surface the reasons before calling it
+        validated = core_wrapper.validate_analysis(analysis_proto)
+        if not validated.valid:
+            raise ValueError("analysis is invalid:\n" + "\n".join(
+                diagnostic.message for diagnostic in validated.diagnostics
+                if diagnostic.severity == release_pb2.Diagnostic.Severity.Value("ERROR")))
         self.release_proto: release_pb2.Release = core_wrapper.compute_release(
             self._make_dataset_proto(),
             analysis_proto,
@@ -274,7 +371,8 @@ class Analysis(object):
 
         return core_wrapper.generate_report(
             analysis_proto,
-            self.release_proto)
+            self.release_proto,
+            self._make_dataset_proto())
 
     def __enter__(self):
         global context
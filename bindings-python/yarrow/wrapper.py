@@ -4,6 +4,7 @@ from yarrow._native_runtime import ffi as ffi_runtime, lib as lib_runtime
 import json
 import ctypes
 
+from . import analysis_pb2
 from . import release_pb2
 
 
@@ -19,31 +20,64 @@ def _serialize_proto(proto, ffi):
     return ffi.new(f"uint8_t[{len(serialized)}]", serialized), len(serialized)
 
 
+def _deserialize_proto(proto_type, byte_buffer, ffi):
+    # ffi.string stops at the first null byte, which is valid inside a serialized proto
+    return proto_type.FromString(ffi.buffer(byte_buffer.data, byte_buffer.len)[:])
+
+
+# the runtime calls back into these, so they must outlive every release
+_callbacks = []
+# buffers returned to the runtime, until the runtime hands them back to be freed
+_buffers = {}
+
+
 class LibraryWrapper(object):
 
-    def compute_epsilon(self, analysis, release):
-        return lib_validator.compute_privacy(
+    def compute_privacy(self, analysis, release):
+        byte_buffer = lib_validator.compute_privacy(
             *_serialize_proto(analysis, ffi_validator),
             *_serialize_proto(release, ffi_validator)
         )
+        return _deserialize_proto(release_pb2.PrivacyUsage, byte_buffer, ffi_validator)
 
-    def validate_analysis(self, analysis):
-        return lib_validator.validate_analysis(
-            *_serialize_proto(analysis, ffi_validator)
+    def infer_constraints(self, analysis, release, constraints):
+        byte_buffer = lib_validator.infer_constraints(
+            *_serialize_proto(analysis, ffi_validator),
+            *_serialize_proto(release, ffi_validator),
+            *_serialize_proto(constraints, ffi_validator)
         )
+        return _deserialize_proto(analysis_pb2.Analysis, byte_buffer, ffi_validator)
 
-    def generate_report(self, analysis, release):
-        byte_buffer = lib_validator.generate_report(
+    def from_accuracy(self, analysis, release, accuracies):
+        byte_buffer = lib_validator.from_accuracy(
             *_serialize_proto(analysis, ffi_validator),
-            *_serialize_proto(release, ffi_validator)
+            *_serialize_proto(release, ffi_validator),
+            *_serialize_proto(accuracies, ffi_validator)
         )
+        return _deserialize_proto(release_pb2.PrivacyUsage, byte_buffer, ffi_validator)
 
-        json_string = ffi_runtime.string(byte_buffer.data, byte_buffer.len)
+    def to_accuracy(self, analysis, release, alpha):
+        byte_buffer = lib_validator.to_accuracy(
+            *_serialize_proto(analysis, ffi_validator),
+            *_serialize_proto(release, ffi_validator),
+            alpha
+        )
+        return _deserialize_proto(release_pb2.Accuracies, byte_buffer, ffi_validator)
 
-        # TODO: why is ffi returning two extra characters: \n\x10, a newline and data link escape control character?
-        json_string = json_string[2:]
+    def validate_analysis(self, analysis):
+        byte_buffer = lib_validator.validate_analysis(
+            *_serialize_proto(analysis, ffi_validator)
+        )
+        return _deserialize_proto(release_pb2.Validated, byte_buffer, ffi_validator)
 
-        return json.loads(json_string)
+    def generate_report(self, analysis, release, dataset):
+        byte_buffer = lib_validator.generate_report(
+            *_serialize_proto(analysis, ffi_validator),
+            *_serialize_proto(release, ffi_validator),
+            *_serialize_proto(dataset, ffi_validator)
+        )
+        report = _deserialize_proto(release_pb2.Report, byte_buffer, ffi_validator)
+        return json.loads(report.value)
 
     def compute_release(self, dataset, analysis, release):
 
@@ -52,5 +86,32 @@ class LibraryWrapper(object):
             *_serialize_proto(analysis, ffi_runtime),
             *_serialize_proto(release, ffi_runtime)
         )
-        serialized_response = ffi_runtime.string(byte_buffer.data, byte_buffer.len)
-        return release_pb2.Release.FromString(serialized_response)
+        response = _deserialize_proto(release_pb2.ReleaseResponse, byte_buffer, ffi_runtime)
+        if response.HasField("error"):
+            error = response.error
+            location = f"node {error.node_id} ({error.component}): " if error.component else ""
+            raise RuntimeError(location + error.message)
+        return response.release
+
+    def register_component(self, name, evaluate):
+        """evaluate Custom components with this name by calling evaluate(arguments), from and to ReleaseNode"""
+
+        @ffi_runtime.callback("ByteBuffer(const uint8_t *, int32_t)")
+        def evaluate_callback(arguments_ptr, arguments_length):
+            try:
+                arguments = release_pb2.ReleaseNode.FromString(ffi_runtime.buffer(arguments_ptr, arguments_length)[:])
+                serialized = evaluate(arguments).SerializeToString()
+            except Exception:
+                # a null buffer tells the runtime the evaluation failed
+                return {"len": 0, "data": ffi_runtime.NULL}
+            data = ffi_runtime.new(f"uint8_t[{len(serialized) or 1}]", serialized)
+            _buffers[int(ffi_runtime.cast("uintptr_t", data))] = data
+            return {"len": len(serialized), "data": data}
+
+        @ffi_runtime.callback("void(ByteBuffer)")
+        def destroy_callback(byte_buffer):
+            _buffers.pop(int(ffi_runtime.cast("uintptr_t", byte_buffer.data)), None)
+
+        _callbacks.extend([evaluate_callback, destroy_callback])
+        if not lib_runtime.register_component(name.encode(), evaluate_callback, destroy_callback):
+            raise ValueError(f"unable to register component {name}")
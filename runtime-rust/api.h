@@ -12,6 +12,12 @@ typedef struct {
   uint8_t *data;
 } ByteBuffer;
 
+typedef ByteBuffer (*EvaluateCallback)(const uint8_t *arguments_ptr, int32_t arguments_length);
+
+typedef void (*DestroyCallback)(ByteBuffer buffer);
+
+bool register_component(const char *name, EvaluateCallback evaluate, DestroyCallback destroy);
+
 ByteBuffer release(const uint8_t *dataset_ptr,
                    int32_t dataset_length,
                    const uint8_t *analysis_ptr,
@@ -1,103 +1,128 @@
 use ndarray::prelude::*;
-use ndarray_stats::SummaryStatisticsExt;
 use ndarray::Zip;
 
 use crate::utilities::noise;
+use crate::base::Error;
+use std::convert::TryFrom;
+use rug::float::Round;
+use rug::ops::Pow;
+use yarrow_validator::snapping;
 
-pub fn dp_mean_laplace(
-    epsilon: f64, num_records: f64,
-    data: ArrayD<f64>,
-    minimum: f64, maximum: f64) -> f64 {
-
-    let sensitivity: f64 = (maximum - minimum) / num_records;
-
-    let mean: f64 = data
-        .mapv(|v| num::clamp(v, minimum, maximum))
-        .mean().unwrap();
-
-    let noise: f64 = noise::sample_laplace(0., sensitivity / epsilon);
-
-    mean + noise
+fn invalid(argument: &str, reason: &str) -> Error {
+    Error::InvalidArgument(argument.to_string(), reason.to_string())
 }
 
-pub fn dp_variance_laplace(
-    epsilon: f64, num_records: f64,
-    data: ArrayD<f64>,
-    minimum: f64, maximum: f64) -> f64 {
+// statistics are divided by the public num_records, so that the sensitivity does not depend on the data
 
-    let sensitivity: f64 = (num_records - 1.0) * ((maximum - minimum) / num_records).powi(2);
-
-    let variance: f64 = data
-        .mapv(|v| num::clamp(v, minimum, maximum))
-        .central_moment(2).unwrap();
-
-    let noise: f64 = noise::sample_laplace(0., sensitivity / epsilon);
-
-    variance + noise
+pub fn mean(num_records: f64, data: ArrayD<f64>, minimum: f64, maximum: f64) -> f64 {
+    data.mapv(|v| num::clamp(v, minimum, maximum)).sum() / num_records
 }
 
-pub fn dp_moment_raw_laplace(
-    epsilon: f64, num_records: f64,
-    data: ArrayD<f64>,
-    minimum: f64, maximum: f64,
-    order: u32) -> f64 {
-
-    let sensitivity: f64 = (maximum - minimum).powi(order as i32) / num_records;
-
-    let moment: f64 = data
-        .mapv(|v| num::clamp(v, minimum, maximum).powi(order as i32))
-        .mean().unwrap();
-
-    let noise: f64 = noise::sample_laplace(0., sensitivity / epsilon);
-
-    moment + noise
+pub fn variance(num_records: f64, data: ArrayD<f64>, minimum: f64, maximum: f64) -> Result<f64, Error> {
+    let data = data.mapv(|v| num::clamp(v, minimum, maximum));
+    let mean: f64 = data.mean().ok_or_else(|| invalid("data", "must not be empty"))?;
+    Ok(data.mapv(|v| (v - mean).powi(2)).sum() / num_records)
 }
 
-pub fn dp_covariance(
-    epsilon: f64, num_records: f64,
-    data_x: ArrayD<f64>, data_y: ArrayD<f64>,
-    minimum_x: f64, minimum_y: f64,
-    maximum_x: f64, maximum_y: f64) -> f64 {
+pub fn moment_raw(num_records: f64, data: ArrayD<f64>, minimum: f64, maximum: f64, order: u32) -> f64 {
+    data.mapv(|v| num::clamp(v, minimum, maximum).powi(order as i32)).sum() / num_records
+}
 
-    let sensitivity: f64 = 2. * (num_records - 1.)
-        / num_records * (maximum_x - minimum_x) * (maximum_y - minimum_y);
+pub fn covariance(num_records: f64,
+                  data_x: ArrayD<f64>, data_y: ArrayD<f64>,
+                  minimum_x: f64, minimum_y: f64,
+                  maximum_x: f64, maximum_y: f64) -> Result<f64, Error> {
 
-    let data_x = data_x.mapv(|v| num::clamp(v, minimum_x, maximum_x)).into_dimensionality::<Ix1>().unwrap();
-    let data_y = data_y.mapv(|v| num::clamp(v, minimum_y, maximum_y)).into_dimensionality::<Ix1>().unwrap();
+    let data_x = data_x.mapv(|v| num::clamp(v, minimum_x, maximum_x)).into_dimensionality::<Ix1>()
+        .map_err(|_| invalid("data_x", "must be one-dimensional"))?;
+    let data_y = data_y.mapv(|v| num::clamp(v, minimum_y, maximum_y)).into_dimensionality::<Ix1>()
+        .map_err(|_| invalid("data_y", "must be one-dimensional"))?;
+    if data_x.len() != data_y.len() {
+        return Err(invalid("data_y", "must have the same length as data_x"));
+    }
 
-    let mean_x = data_x.mean().unwrap();
-    let mean_y = data_y.mean().unwrap();
+    let mean_x = data_x.mean().ok_or_else(|| invalid("data_x", "must not be empty"))?;
+    let mean_y = data_y.mean().ok_or_else(|| invalid("data_y", "must not be empty"))?;
 
     let mut products = Array1::<f64>::zeros(data_x.len());
     Zip::from(&mut products).and(&data_x).and(&data_y)
         .apply(|total, &x, &y| *total += (x - mean_x) * (y - mean_y));
 
-    let covariance = products.mean().unwrap();
-    let noise: f64 = noise::sample_laplace(0., sensitivity / epsilon);
+    Ok(products.sum() / num_records)
+}
+
+// noise added to a statistic, with its scale calibrated to the sensitivity of the statistic
+pub enum Noise {
+    Laplace {scale: f64},
+    // standard deviation of the noise
+    Gaussian {scale: f64},
+    // calibrated by the snapping mechanism, where bound is the snapping bound on the statistic, in its own units
+    Snapping {epsilon: f64, bound: f64, sensitivity: f64}
+}
 
-    covariance + noise
+pub fn privatize(statistic: f64, noise: &Noise) -> Result<f64, Error> {
+    Ok(statistic + match noise {
+        Noise::Laplace {scale} => noise::sample_laplace(0., *scale),
+        Noise::Gaussian {scale} => noise::sample_gaussian(0., *scale),
+        Noise::Snapping {epsilon, bound, sensitivity} =>
+            noise::sample_snapping_noise(&statistic, epsilon, bound, sensitivity, &snapping::PRECISION)?
+    })
 }
 
+//pub fn dp_histogram(
+//    epsilon: f64, num_records: f64,
+//    data_x: ArrayD<f64>
+//) -> u64 {
+//
+//}
+
+// bits of the fractional part of the base of the exponential mechanism
+const EXPONENTIAL_PRECISION: u32 = 64;
+// weights smaller than 2^-EXPONENTIAL_TAIL of the largest weight are raised to that size
+const EXPONENTIAL_TAIL: u32 = 64;
+// largest number of bits in a weight, which bounds the memory and time of selection
+const EXPONENTIAL_MAX_BITS: u32 = 1 << 24;
+
 pub fn dp_exponential<T>(
                          epsilon: f64,
                          data: ArrayD<T>,
-                         utility: &dyn Fn(&T) -> f64,
+                         utility: &dyn Fn(&T) -> i64,
                          sensitivity: f64
-                         ) -> T where T: Copy, {
-    /// Returns data element according to the exponential mechanism
+                         ) -> Result<T, Error> where T: Clone, {
+    /// Returns data element according to an exact exponential mechanism
     ///
     /// # Arguments
     ///
     /// * `epsilon` - privacy loss parameter
     /// * `data` - data from which user wants an element returned
-    /// * `utility` - utility function used within the exponential mechanism
+    /// * `utility` - integer utility function used within the exponential mechanism
     /// * `sensitivity` - sensitivity of utility function
     ///
-    /// NOTE: This implementation is likely non-private because of the difference between theory on
-    ///       the real numbers and floating-point numbers. See https://arxiv.org/abs/1912.04222 for
-    ///       more information on the problem and a proposed fix.
+    /// This is the base-2 exponential mechanism of Ilvento, https://arxiv.org/abs/1912.04222, with the base
+    /// 2^-eta = (x / 2^y)^z for x = p, y = 64 and z = 1, where p = ceil(2^64 exp(-epsilon / (2 sensitivity))).
+    /// Each element is weighted by base^min(max utility - utility, cap). The weights are computed exactly,
+    /// with arbitrary-precision integers, and sampled with exact uniform integers, so that the release is not exposed
+    /// to floating-point attacks.
     ///
-    /// TODO: Implement Christina's base-2 exponential mechanism?
+    /// Rounding the base up to p / 2^64 is private. Write b = p / 2^64 >= exp(-epsilon / (2 sensitivity)).
+    /// Shifting every utility by the maximum utility cancels in the normalization, so each element x is selected
+    /// with probability b^-u(x) / sum_y b^-u(y). Between neighboring datasets each utility changes by at most
+    /// the sensitivity, so the numerator changes by a factor of at most b^-sensitivity, and so does the denominator.
+    /// The probability of any element changes by a factor of at most b^(-2 sensitivity) = exp(epsilon'),
+    /// where epsilon' = 2 sensitivity ln(2^64 / p) <= 2 sensitivity ln(exp(epsilon / (2 sensitivity))) = epsilon.
+    /// When p would reach 2^64, the base is one and the selection is uniform, which is 0-DP.
+    ///
+    /// Capping the distance is also private. The cap is the smallest distance at which the weight falls below 2^-64,
+    /// and depends only on epsilon and the sensitivity. The capped weights are the mechanism on the utility
+    /// u'(x) = max(u(x), max_y u(y) - cap), which has the same maximum as u. Each term of the maximum changes by at most
+    /// the sensitivity between neighboring datasets, so u' has the same sensitivity as u, and the argument above applies.
+    /// The cap raises the probability of each element with a capped distance by less than 2^-64 relative to the most useful element,
+    /// and bounds each weight at 64 * cap bits, regardless of the range of the utilities.
+    ///
+    /// # Errors
+    ///
+    /// * `epsilon` is too small for the weights to fit within 2^24 bits
+    /// * the utilities are too far apart to represent their differences
     ///
     /// # Example
     /// ```
@@ -105,42 +130,156 @@ pub fn dp_exponential<T>(
     /// use ndarray::prelude::*;
     ///
     /// // create utility function
-    /// pub fn utility(x:&f64) -> f64 {
-    ///     let util = *x as f64;
+    /// pub fn utility(x:&i64) -> i64 {
+    ///     let util = *x;
     ///     return util;
     /// }
     ///
     /// // create sample data
-    /// let xs: ArrayD<f64> = arr1(&[1., 2., 3., 4., 5.]).into_dyn();
-    /// let ans:f64 = algorithms::dp_exponential(1.0, xs, &utility, 1.0);
+    /// let xs: ArrayD<i64> = arr1(&[1, 2, 3, 4, 5]).into_dyn();
+    /// let ans:i64 = algorithms::dp_exponential(1.0, xs, &utility, 1.0).unwrap();
     /// println!("{}", ans);
     /// ```
 
-    // get vector of e^(util), then use to find probabilities
-    let e_util_vec: Vec<f64> = data.iter().map(|x| std::f64::consts::E.powf(epsilon * utility(x) / (2.0 * sensitivity))).collect();
-    let sum_e_util_vec:f64 = e_util_vec.iter().sum();
-    let probability_vec: Vec<f64> = e_util_vec.iter().map(|x| x / sum_e_util_vec).collect();
+    if data.is_empty() {
+        return Err(Error::InvalidArgument("candidates".to_string(), "must not be empty".to_string()));
+    }
+
+    // numerator of the base, over a denominator of 2^EXPONENTIAL_PRECISION
+    // the exponent is rounded down and the exponential rounded up, so that the base is never below exp(-epsilon / (2 sensitivity))
+    let denominator = rug::Integer::from(1) << EXPONENTIAL_PRECISION;
+    let exponent = rug::Float::with_val_round(
+        2 * EXPONENTIAL_PRECISION, rug::Float::with_val(2 * EXPONENTIAL_PRECISION, epsilon) / (2.0 * sensitivity), Round::Down).0;
+    let mut base = -exponent;
+    base.exp_round(Round::Up);
+    base <<= EXPONENTIAL_PRECISION;
+    let numerator = base.to_integer_round(Round::Up)
+        .map(|(numerator, _)| numerator)
+        .filter(|numerator| *numerator < denominator)
+        // a base of one selects uniformly
+        .unwrap_or_else(|| denominator.clone());
+
+    // smallest distance at which base^cap <= 2^-EXPONENTIAL_TAIL, where -ln(base) = -ln(1 - (denominator - numerator) / denominator)
+    // the cap need not be exact to be private, only independent of the data
+    let log_base = (-rug::Integer::from(&denominator - &numerator).to_f64() / denominator.to_f64()).ln_1p();
+    let cap = match log_base < 0. {
+        true => (f64::from(EXPONENTIAL_TAIL) * 2_f64.ln() / -log_base).ceil(),
+        false => 0.
+    };
+    if cap * f64::from(EXPONENTIAL_PRECISION) > f64::from(EXPONENTIAL_MAX_BITS) {
+        return Err(Error::InvalidArgument("epsilon".to_string(), format!(
+            "{} is too small to select exactly with a utility sensitivity of {}", epsilon, sensitivity)));
+    }
+    let cap = cap as i64;
 
-    // generate cumulative probability distribution
-    let cumulative_probability_vec = probability_vec.iter().scan(0.0, |sum, i| {*sum += i; Some(*sum)}).collect::<Vec<_>>();
+    // distance of each utility from the maximum, so that the most useful element has the largest weight
+    let utilities: Vec<i64> = data.iter().map(utility).collect();
+    let max_util: i64 = *utilities.iter().max().unwrap();
+    let distances: Vec<u32> = utilities.iter()
+        .map(|util| max_util.checked_sub(*util)
+            .and_then(|distance| u32::try_from(distance.min(cap)).ok())
+            .ok_or_else(|| Error::InvalidArgument("utility".to_string(), format!(
+                "values {} and {} are too far apart to select between", max_util, util))))
+        .collect::<Result<Vec<u32>, Error>>()?;
+    let max_distance: u32 = *distances.iter().max().unwrap();
 
-    // generate uniform random number on (0,1)
-    let unif:f64 = noise::sample_uniform_snapping();
+    // weights numerator^distance / denominator^distance, scaled by denominator^max_distance to clear the fractions
+    let weights: Vec<rug::Integer> = distances.iter()
+        .map(|distance| rug::Integer::from((&numerator).pow(*distance)) << (EXPONENTIAL_PRECISION * (max_distance - distance)))
+        .collect();
+    let total = weights.iter().fold(rug::Integer::new(), |total, weight| total + weight);
 
-    // sample an element relative to its probability
-    let mut return_index = 0;
-    for i in 0..cumulative_probability_vec.len() {
-        if unif <= cumulative_probability_vec[i] {
-            return_index = i;
-            break
+    // sample an element relative to its weight
+    let mut sample = noise::sample_uniform_integer(&total);
+    for (index, weight) in weights.iter().enumerate() {
+        if sample < *weight {
+            return Ok(data[index].clone());
         }
+        sample -= weight;
     }
-    return data[return_index]
+    Ok(data[weights.len() - 1].clone())
 }
 
-//pub fn dp_histogram(
-//    epsilon: f64, num_records: f64,
-//    data_x: ArrayD<f64>
-//) -> u64 {
-//
-//}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // chi-squared statistic of the selection counts against the expected probabilities
+    fn chi_squared(counts: &[u64], probabilities: &[f64]) -> f64 {
+        let trials = counts.iter().sum::<u64>() as f64;
+        counts.iter().zip(probabilities)
+            .map(|(count, probability)| (*count as f64 - trials * probability).powi(2) / (trials * probability))
+            .sum()
+    }
+
+    fn frequencies(candidates: &[i64], utility: &dyn Fn(&i64) -> i64, epsilon: f64, trials: u64) -> Vec<u64> {
+        let mut counts = vec![0; candidates.len()];
+        for _ in 0..trials {
+            let selected = dp_exponential(epsilon, arr1(candidates).into_dyn(), utility, 1.).unwrap();
+            counts[candidates.iter().position(|candidate| *candidate == selected).unwrap()] += 1;
+        }
+        counts
+    }
+
+    // critical value of the chi-squared distribution with 3 degrees of freedom at p = 1e-4
+    const CHI_SQUARED_3: f64 = 21.108;
+
+    #[test]
+    fn single_candidate() {
+        assert_eq!(dp_exponential(1., arr1(&[7_i64]).into_dyn(), &|x: &i64| *x, 1.).unwrap(), 7);
+    }
+
+    #[test]
+    fn no_candidates() {
+        assert!(dp_exponential(1., arr1(&[] as &[i64]).into_dyn(), &|x: &i64| *x, 1.).is_err());
+    }
+
+    #[test]
+    fn equal_utilities_are_uniform() {
+        let counts = frequencies(&[0, 1, 2, 3], &|_| 5, 1., 40_000);
+        assert!(chi_squared(&counts, &[0.25; 4]) < CHI_SQUARED_3, "{:?}", counts);
+    }
+
+    #[test]
+    fn selection_frequencies() {
+        // probabilities proportional to exp(epsilon u / (2 sensitivity)) with epsilon = 1
+        let weights: Vec<f64> = (0..4).map(|u| (f64::from(u) / 2.).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        let probabilities: Vec<f64> = weights.iter().map(|weight| weight / total).collect();
+
+        let counts = frequencies(&[0, 1, 2, 3], &|x| *x, 1., 40_000);
+        assert!(chi_squared(&counts, &probabilities) < CHI_SQUARED_3, "{:?}", counts);
+    }
+
+    #[test]
+    fn wide_utilities_are_capped() {
+        // the distance of 10^12 is capped, so the weight of the first candidate is below 2^-64 of the second
+        let counts = frequencies(&[0, 1_000_000_000_000], &|x| *x, 1., 1_000);
+        assert_eq!(counts, vec![0, 1_000]);
+    }
+
+    #[test]
+    fn distance_overflow() {
+        assert!(dp_exponential(1., arr1(&[i64::MIN, i64::MAX]).into_dyn(), &|x: &i64| *x, 1.).is_err());
+    }
+
+    #[test]
+    fn small_epsilon() {
+        assert!(dp_exponential(1e-12, arr1(&[0_i64, 1]).into_dyn(), &|x: &i64| *x, 1.).is_err());
+    }
+
+    #[test]
+    fn malformed_data_is_reported() {
+        let empty = ArrayD::<f64>::zeros(IxDyn(&[0]));
+        let matrix = ArrayD::<f64>::zeros(IxDyn(&[2, 2]));
+        let pair = arr1(&[1., 2.]).into_dyn();
+        let triple = arr1(&[1., 2., 3.]).into_dyn();
+        let message = |result: Result<f64, Error>| result.unwrap_err().to_string();
+
+        assert_eq!(message(variance(1., empty.clone(), 0., 1.)), "argument \"data\" must not be empty");
+        assert_eq!(message(covariance(1., matrix, pair.clone(), 0., 0., 1., 1.)), "argument \"data_x\" must be one-dimensional");
+        assert_eq!(message(covariance(1., pair.clone(), triple, 0., 0., 1., 1.)), "argument \"data_y\" must have the same length as data_x");
+        assert_eq!(message(covariance(1., empty.clone(), empty, 0., 0., 1., 1.)), "argument \"data_x\" must not be empty");
+        assert_eq!(covariance(2., pair.clone(), pair, 0., 0., 2., 2.).unwrap(), 0.25);
+    }
+}
@@ -1,5 +1,6 @@
 extern crate yarrow_validator;
 use yarrow_validator::yarrow;
+use yarrow_validator::privacy;
 
 use ndarray::prelude::*;
 
@@ -27,13 +28,78 @@ pub type GraphEvaluation = HashMap<u32, NodeEvaluation>;
 // arguments to a node prior to evaluation
 pub type NodeArguments<'a> = HashMap<String, &'a FieldEvaluation>;
 
-pub fn get_arguments<'a>(component: &yarrow::Component, graph_evaluation: &'a GraphEvaluation) -> NodeArguments<'a> {
+// reasons a component may fail to evaluate
+#[derive(Debug)]
+pub enum Error {
+    // the argument is absent, or its source did not produce the field
+    MissingArgument(String),
+    // the argument has an unsupported type or is empty
+    InvalidArgument(String, String),
+    // no evaluation is implemented or registered for the component
+    Unimplemented,
+    // the component cannot evaluate the requested mechanism
+    UnsupportedMechanism(String),
+    // the dataset could not be read
+    Dataset(String),
+    // a proto message is malformed
+    Decode(String),
+    // the host failed to evaluate a custom component
+    Custom(String)
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::MissingArgument(argument) => write!(f, "argument \"{}\" is missing", argument),
+            Error::InvalidArgument(argument, reason) => write!(f, "argument \"{}\" {}", argument, reason),
+            Error::Unimplemented => write!(f, "not implemented by the runtime"),
+            Error::UnsupportedMechanism(mechanism) => write!(f, "mechanism {} is not supported", mechanism),
+            Error::Dataset(message) => write!(f, "{}", message),
+            Error::Decode(message) => write!(f, "malformed proto: {}", message),
+            Error::Custom(message) => write!(f, "{}", message)
+        }
+    }
+}
+
+// error raised while evaluating one node of the graph
+#[derive(Debug)]
+pub struct NodeError {
+    pub node_id: u32,
+    pub component: String,
+    pub error: Error
+}
+
+impl NodeError {
+    pub fn new(node_id: u32, component: Option<&yarrow::Component>, error: Error) -> NodeError {
+        NodeError {node_id, component: get_component_name(component), error}
+    }
+}
+
+// name of the component type, or of the registered component if custom, or empty if unknown
+pub fn get_component_name(component: Option<&yarrow::Component>) -> String {
+    component.and_then(|component| component.value.as_ref())
+        .map(|value| match value {
+            yarrow::component::Value::Custom(x) => x.name.as_str(),
+            _ => yarrow_validator::components::get_component(value).name()
+        })
+        .unwrap_or("").to_string()
+}
+
+impl From<NodeError> for yarrow::Error {
+    fn from(error: NodeError) -> yarrow::Error {
+        yarrow::Error {node_id: error.node_id, component: error.component, message: error.error.to_string()}
+    }
+}
+
+pub fn get_arguments<'a>(component: &yarrow::Component, graph_evaluation: &'a GraphEvaluation) -> Result<NodeArguments<'a>, Error> {
     let mut arguments = NodeArguments::new();
-    component.arguments.iter().for_each(|(field_id, field)| {
-        let evaluation: &'a FieldEvaluation = graph_evaluation.get(&field.source_node_id).unwrap().get(&field.source_field).unwrap().to_owned();
+    for (field_id, field) in &component.arguments {
+        let evaluation: &'a FieldEvaluation = graph_evaluation.get(&field.source_node_id)
+            .and_then(|node_evaluation| node_evaluation.get(&field.source_field))
+            .ok_or_else(|| Error::MissingArgument(field_id.to_owned()))?;
         arguments.insert(field_id.to_owned(), evaluation);
-    });
-    arguments
+    }
+    Ok(arguments)
 }
 
 pub fn get_release_nodes(analysis: &yarrow::Analysis) -> HashSet<u32> {
@@ -48,11 +114,14 @@ pub fn get_release_nodes(analysis: &yarrow::Analysis) -> HashSet<u32> {
 
     let graph: &HashMap<u32, yarrow::Component> = &analysis.graph;
 
-    while !node_queue.is_empty() {
-        let node_id = node_queue.pop_front().unwrap();
-        let component = graph.get(&node_id).unwrap();
+    while let Some(node_id) = node_queue.pop_front() {
+        // arguments missing from the graph are reported when the graph is executed
+        let component = match graph.get(node_id) {
+            Some(component) => component,
+            None => continue
+        };
 
-        if is_privatizer(&component) {
+        if privacy::is_privatizer(component) {
             release_node_ids.insert(*node_id);
         }
         else {
@@ -83,17 +152,10 @@ pub fn get_sinks(analysis: &yarrow::Analysis) -> HashSet<u32> {
     return node_ids.to_owned();
 }
 
-pub fn is_privatizer(component: &yarrow::Component) -> bool {
-    use yarrow::component::Value::*;
-    match component.to_owned().value.unwrap() {
-        Dpmean(_x) => true,
-        _ => false
-    }
-}
-
 pub fn execute_graph(analysis: &yarrow::Analysis,
                      release: &yarrow::Release,
-                     dataset: &yarrow::Dataset) -> yarrow::Release {
+                     dataset: &yarrow::Dataset,
+                     registry: &components::Registry) -> Result<yarrow::Release, NodeError> {
 
     let node_ids_release: HashSet<u32> = get_release_nodes(&analysis);
 
@@ -101,9 +163,12 @@ pub fn execute_graph(analysis: &yarrow::Analysis,
     let mut traversal = Vec::new();
     traversal.extend(get_sinks(&analysis).into_iter());
 
-    let mut evaluations = release_to_evaluations(release);
+    let mut evaluations = release_to_evaluations(release)?;
     let graph: &HashMap<u32, yarrow::Component> = &analysis.graph;
 
+    let privacy_definition = analysis.privacy_definition.clone().unwrap_or_default();
+    let context = components::Context {dataset, privacy_definition: &privacy_definition};
+
     // track node parents
     let mut parents = HashMap::<u32, HashSet<u32>>::new();
     graph.iter().for_each(|(node_id, component)| {
@@ -113,9 +178,9 @@ pub fn execute_graph(analysis: &yarrow::Analysis,
         })
     });
 
-    while !traversal.is_empty() {
-        let node_id: u32 = *traversal.last().unwrap();
-        let component = graph.get(&node_id).unwrap();
+    while let Some(&node_id) = traversal.last() {
+        let component = graph.get(&node_id)
+            .ok_or_else(|| NodeError::new(node_id, None, Error::Decode("node is not in the graph".to_string())))?;
         let arguments = component.to_owned().arguments;
 
         // discover if any dependencies remain uncomputed
@@ -132,16 +197,16 @@ pub fn execute_graph(analysis: &yarrow::Analysis,
         if evaluable {
             traversal.pop();
 
-            evaluations.insert(node_id, execute_component(
-                &graph.get(&node_id).unwrap(), &evaluations, &dataset));
+            let evaluation = execute_component(component, &evaluations, &context, registry)
+                .map_err(|error| NodeError::new(node_id, Some(component), error))?;
+            evaluations.insert(node_id, evaluation);
 
             // remove references to parent node, and if empty and private
             for argument in arguments.values() {
                 let argument_node_id = &(argument.source_node_id);
-                let tempval = parents.get_mut(argument_node_id).unwrap();
-                tempval.remove(&node_id);
-                if parents.get(argument_node_id).unwrap().len() == 0 {
-                    if !node_ids_release.contains(argument_node_id) {
+                if let Some(argument_parents) = parents.get_mut(argument_node_id) {
+                    argument_parents.remove(&node_id);
+                    if argument_parents.is_empty() && !node_ids_release.contains(argument_node_id) {
                         evaluations.remove(argument_node_id);
                         // parents.remove(argument_node_id); // optional
                     }
@@ -149,102 +214,100 @@ pub fn execute_graph(analysis: &yarrow::Analysis,
             }
         }
     }
-    evaluations_to_release(&evaluations)
+    Ok(evaluations_to_release(&evaluations))
 }
 
 pub fn execute_component(component: &yarrow::Component,
                          evaluations: &GraphEvaluation,
-                         dataset: &yarrow::Dataset) -> NodeEvaluation {
-
-    let arguments = get_arguments(&component, &evaluations);
-
-    match component.to_owned().value.unwrap() {
-        yarrow::component::Value::Literal(x) => components::component_literal(&x),
-        yarrow::component::Value::Datasource(x) => components::component_datasource(&x, &dataset, &arguments),
-        yarrow::component::Value::Add(x) => components::component_add(&x, &arguments),
-        yarrow::component::Value::Subtract(x) => components::component_subtract(&x, &arguments),
-        yarrow::component::Value::Divide(x) => components::component_divide(&x, &arguments),
-        yarrow::component::Value::Multiply(x) => components::component_multiply(&x, &arguments),
-        yarrow::component::Value::Power(x) => components::component_power(&x, &arguments),
-        yarrow::component::Value::Negate(x) => components::component_negate(&x, &arguments),
-        yarrow::component::Value::Bin(x) => components::component_bin(&x, &arguments),
-        yarrow::component::Value::Count(x) => components::component_count(&x, &arguments),
-        // yarrow::component::Value::Histogram(x) => components::component_histogram(&x, &arguments),
-        yarrow::component::Value::Median(x) => components::component_median(&x, &arguments),
-        yarrow::component::Value::Sum(x) => components::component_sum(&x, &arguments),
-        yarrow::component::Value::Dpmean(x) => components::component_dp_mean(&x, &arguments),
-        yarrow::component::Value::Dpvariance(x) => components::component_dp_variance(&x, &arguments),
-        yarrow::component::Value::Dpmomentraw(x) => components::component_dp_moment_raw(&x, &arguments),
-        yarrow::component::Value::Dpcovariance(x) => components::component_dp_covariance(&x, &arguments),
-        _ => NodeEvaluation::new()
+                         context: &components::Context,
+                         registry: &components::Registry) -> Result<NodeEvaluation, Error> {
+
+    let arguments = get_arguments(&component, &evaluations)?;
+
+    match component.value.as_ref() {
+        Some(value) => registry.evaluate(value, &arguments, context),
+        None => Err(Error::Decode("component must have a value".to_string()))
     }
 }
 
-pub fn get_f64(arguments: &NodeArguments, column: &str) -> f64 {
-    match arguments.get(column).unwrap() {
-        FieldEvaluation::Bool(x) => Ok(if *x.first().unwrap() {1.} else {0.}),
-        FieldEvaluation::I64(x) => Ok(f64::from(*x.first().unwrap() as i32)),
-        FieldEvaluation::F64(x) => Ok(x.first().unwrap().to_owned()),
-        _ => Err(column.to_string() +" must be numeric")
-    }.unwrap()
+pub fn get_argument<'a>(arguments: &NodeArguments<'a>, column: &str) -> Result<&'a FieldEvaluation, Error> {
+    arguments.get(column).copied().ok_or_else(|| Error::MissingArgument(column.to_string()))
+}
+
+fn get_first<T: Clone>(array: &ArrayD<T>, column: &str) -> Result<T, Error> {
+    array.first().cloned().ok_or_else(|| Error::InvalidArgument(column.to_string(), "must not be empty".to_string()))
+}
+
+fn invalid(column: &str, reason: &str) -> Error {
+    Error::InvalidArgument(column.to_string(), reason.to_string())
+}
+
+pub fn get_f64(arguments: &NodeArguments, column: &str) -> Result<f64, Error> {
+    match get_argument(arguments, column)? {
+        FieldEvaluation::Bool(x) => Ok(if get_first(x, column)? {1.} else {0.}),
+        FieldEvaluation::I64(x) => Ok(get_first(x, column)? as f64),
+        FieldEvaluation::F64(x) => get_first(x, column),
+        _ => Err(invalid(column, "must be numeric"))
+    }
 }
 
-pub fn get_array_f64(arguments: &NodeArguments, column: &str) -> ArrayD<f64> {
-    match arguments.get(column).unwrap() {
+pub fn get_array_f64(arguments: &NodeArguments, column: &str) -> Result<ArrayD<f64>, Error> {
+    match get_argument(arguments, column)? {
         FieldEvaluation::Bool(x) => Ok(x.mapv(|v| if v {1.} else {0.})),
-        FieldEvaluation::I64(x) => Ok(x.mapv(|v| f64::from(v as i32))),
+        FieldEvaluation::I64(x) => Ok(x.mapv(|v| v as f64)),
         FieldEvaluation::F64(x) => Ok(x.to_owned()),
-        _ => Err(column.to_string() +" must be numeric")
-    }.unwrap()
+        _ => Err(invalid(column, "must be numeric"))
+    }
 }
 
-pub fn get_str(arguments: &NodeArguments, column: &str) -> String {
-    match arguments.get(column).unwrap() {
-        FieldEvaluation::Str(x) => Ok(x.first().unwrap().to_owned()),
-        _ => Err(column.to_string() +" must be string")
-    }.unwrap()
+pub fn get_str(arguments: &NodeArguments, column: &str) -> Result<String, Error> {
+    match get_argument(arguments, column)? {
+        FieldEvaluation::Str(x) => get_first(x, column),
+        _ => Err(invalid(column, "must be string"))
+    }
 }
 
-pub fn get_array_str(arguments: &NodeArguments, column: &str) -> ArrayD<String> {
-    match arguments.get(column).unwrap() {
+pub fn get_array_str(arguments: &NodeArguments, column: &str) -> Result<ArrayD<String>, Error> {
+    match get_argument(arguments, column)? {
         FieldEvaluation::Str(x) => Ok(x.to_owned()),
-        _ => Err(column.to_string() + " must be string")
-    }.unwrap()
+        _ => Err(invalid(column, "must be string"))
+    }
 }
 
-pub fn get_bool(arguments: &NodeArguments, column: &str) -> bool {
-    match arguments.get(column).unwrap() {
+pub fn get_bool(arguments: &NodeArguments, column: &str) -> Result<bool, Error> {
+    match get_argument(arguments, column)? {
         // maybe want to figure out how to accept wider range of bool arguments -- for now, comment out
         // (FieldEvaluation::F64(x) && (*x.first().unwrap() == 1. || x.first().unwrap() == 0.)) => Ok(if *x.first().unwrap() == 1. {true} else *x.first().unwrap() == 0. {false}),
         // (FieldEvaluation::I64(x) && (*x.first().unwrap() == 1 || x.first().unwrap() == 0)) => Ok(if *x.first().unwrap() == 1 {true} else *x.first().unwrap() == 0 {false}),
         // (FieldEvaluation::Str(x) && (*x.first().unwrap() == "true" || x.first().unwrap() == "false")) => Ok(x.first().parse::<bool>().unwrap().to_owned()),
-        FieldEvaluation::Bool(x) => Ok(x.first().unwrap().to_owned()),
-        _ => Err(column.to_string() +" must be boolean")
-    }.unwrap()
+        FieldEvaluation::Bool(x) => get_first(x, column),
+        _ => Err(invalid(column, "must be boolean"))
+    }
 }
 
-pub fn get_array_bool(arguments: &NodeArguments, column: &str) -> ArrayD<bool> {
-    match arguments.get(column).unwrap() {
+pub fn get_array_bool(arguments: &NodeArguments, column: &str) -> Result<ArrayD<bool>, Error> {
+    match get_argument(arguments, column)? {
         // maybe want to figure out how to accept wider range of bool arguments -- for now, comment out
         // (FieldEvaluation::F64(x) && (x.mapv(|v| vec![0., 1.].contains(v)).all(|v| v == true))) => Ok(x.mapv(|v| if v == 1. {true} else if {false})),
         // (FieldEvaluation::I64(x) && (*x.mapv(|v| vec![0, 1].contains(v)).all(|v| v == true))) => Ok(x.mapv(|v| if v == 1 {true} else if v == 0 {false})),
         // (FieldEvaluation::Str(x) && (*x.mapv(|v| vec!["false","true"].contains(v)).all(|v| v == true))) => Ok(x.mapv(|v| if v == "true" {true} else if v == "false" {false})),
         FieldEvaluation::Bool(x) => Ok(x.to_owned()),
-        _ => Err(column.to_string() + " must be boolean")
-    }.unwrap()
+        _ => Err(invalid(column, "must be boolean"))
+    }
 }
 
-pub fn release_to_evaluations(release: &yarrow::Release) -> GraphEvaluation {
+pub fn release_to_evaluations(release: &yarrow::Release) -> Result<GraphEvaluation, NodeError> {
     let mut evaluations = GraphEvaluation::new();
 
     for (node_id, node_release) in &release.values {
         let mut evaluations_node = NodeEvaluation::new();
         for (field_id, field_release) in &node_release.values {
-            evaluations_node.insert(field_id.to_owned(), parse_proto_array(&field_release));
+            evaluations_node.insert(field_id.to_owned(), parse_proto_array(&field_release)
+                .map_err(|error| NodeError::new(*node_id, None, error))?);
         }
         evaluations.insert(*node_id, evaluations_node);
     }
-    evaluations
+    Ok(evaluations)
 }
 
 pub fn evaluations_to_release(evaluations: &GraphEvaluation) -> yarrow::Release {
@@ -264,21 +327,23 @@ pub fn evaluations_to_release(evaluations: &GraphEvaluation) -> yarrow::Release
     }
 }
 
-pub fn parse_proto_array(value: &yarrow::ArrayNd) -> FieldEvaluation {
+pub fn parse_proto_array(value: &yarrow::ArrayNd) -> Result<FieldEvaluation, Error> {
     let value = value.to_owned();
     let shape: Vec<usize> = value.shape.iter().map(|x| *x as usize).collect();
-    match value.data.unwrap() {
-        yarrow::array_nd::Data::Bytes(x) =>
-            FieldEvaluation::Bytes(Array::from_shape_vec(shape, x).unwrap().into_dyn()),
-        yarrow::array_nd::Data::Bool(x) =>
-            FieldEvaluation::Bool(Array::from_shape_vec(shape, x.data).unwrap().into_dyn()),
-        yarrow::array_nd::Data::I64(x) =>
-            FieldEvaluation::I64(Array::from_shape_vec(shape, x.data).unwrap().into_dyn()),
-        yarrow::array_nd::Data::F64(x) =>
-            FieldEvaluation::F64(Array::from_shape_vec(shape, x.data).unwrap().into_dyn()),
-        yarrow::array_nd::Data::String(x) =>
-            FieldEvaluation::Str(Array::from_shape_vec(shape, x.data).unwrap().into_dyn()),
-    }
+    let decode = |error: ndarray::ShapeError| Error::Decode(format!("array does not match its shape: {}", error));
+    Ok(match value.data {
+        Some(yarrow::array_nd::Data::Bytes(x)) =>
+            FieldEvaluation::Bytes(Array::from_shape_vec(shape, x).map_err(decode)?.into_dyn()),
+        Some(yarrow::array_nd::Data::Bool(x)) =>
+            FieldEvaluation::Bool(Array::from_shape_vec(shape, x.data).map_err(decode)?.into_dyn()),
+        Some(yarrow::array_nd::Data::I64(x)) =>
+            FieldEvaluation::I64(Array::from_shape_vec(shape, x.data).map_err(decode)?.into_dyn()),
+        Some(yarrow::array_nd::Data::F64(x)) =>
+            FieldEvaluation::F64(Array::from_shape_vec(shape, x.data).map_err(decode)?.into_dyn()),
+        Some(yarrow::array_nd::Data::String(x)) =>
+            FieldEvaluation::Str(Array::from_shape_vec(shape, x.data).map_err(decode)?.into_dyn()),
+        None => return Err(Error::Decode("array has no data".to_string()))
+    })
 }
 
 pub fn serialize_proto_array(evaluation: &FieldEvaluation) -> yarrow::ArrayNd {
@@ -324,3 +389,51 @@ pub fn serialize_proto_array(evaluation: &FieldEvaluation) -> yarrow::ArrayNd {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_are_checked() {
+        let empty = FieldEvaluation::F64(ArrayD::from_shape_vec(IxDyn(&[0]), vec![]).unwrap());
+        let text = FieldEvaluation::Str(arr0("ten".to_string()).into_dyn());
+        let flag = FieldEvaluation::Bool(arr0(true).into_dyn());
+        let arguments: NodeArguments = [("empty", &empty), ("text", &text), ("flag", &flag)].iter()
+            .map(|(name, value)| (name.to_string(), *value)).collect();
+
+        assert_eq!(get_f64(&arguments, "flag").unwrap(), 1.);
+        assert_eq!(get_f64(&arguments, "minimum").unwrap_err().to_string(), "argument \"minimum\" is missing");
+        assert_eq!(get_f64(&arguments, "empty").unwrap_err().to_string(), "argument \"empty\" must not be empty");
+        assert_eq!(get_f64(&arguments, "text").unwrap_err().to_string(), "argument \"text\" must be numeric");
+        assert!(get_array_f64(&arguments, "text").is_err());
+    }
+
+    #[test]
+    fn integers_are_converted_exactly() {
+        let large = FieldEvaluation::I64(arr1(&[1_i64 << 40, -(1_i64 << 40)]).into_dyn());
+        let arguments: NodeArguments = [("large".to_string(), &large)].iter().cloned().collect();
+
+        assert_eq!(get_f64(&arguments, "large").unwrap(), 2_f64.powi(40));
+        assert_eq!(get_array_f64(&arguments, "large").unwrap(), arr1(&[2_f64.powi(40), -2_f64.powi(40)]).into_dyn());
+    }
+
+    #[test]
+    fn release_nodes_ignore_missing_arguments() {
+        let dp_mean = yarrow::Component {
+            arguments: vec![("data".to_string(), yarrow::component::Field {source_node_id: 1, source_field: "data".to_string()})]
+                .into_iter().collect(),
+            value: Some(yarrow::component::Value::Dpmean(yarrow::DpMean::default()))
+        };
+        let negate = yarrow::Component {
+            arguments: vec![("data".to_string(), yarrow::component::Field {source_node_id: 3, source_field: "data".to_string()})]
+                .into_iter().collect(),
+            value: Some(yarrow::component::Value::Negate(yarrow::Negate {}))
+        };
+        let analysis = yarrow::Analysis {
+            graph: vec![(2, dp_mean), (4, negate)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert_eq!(get_release_nodes(&analysis), vec![2].into_iter().collect());
+    }
+}
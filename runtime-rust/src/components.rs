@@ -9,6 +9,7 @@ use std::str::FromStr;
 use crate::algorithms;
 use crate::utilities;
 use std::boxed::Box;
+use std::sync::{OnceLock, RwLock};
 
 extern crate num;
 
@@ -20,204 +21,479 @@ macro_rules! hashmap {
     }}
 }
 
-pub fn component_literal(x: &yarrow::Literal) -> NodeEvaluation {
-//    println!("literal");
-    hashmap!["data".to_owned() => parse_proto_array(&x.to_owned().value.unwrap())]
+// state shared by every component evaluated in a graph
+pub struct Context<'a> {
+    pub dataset: &'a yarrow::Dataset,
+    pub privacy_definition: &'a yarrow::PrivacyDefinition
 }
 
-pub fn component_datasource(datasource: &yarrow::DataSource, dataset: &yarrow::Dataset, arguments: &NodeArguments) -> NodeEvaluation {
-//    println!("datasource");
+// evaluation of one type of component, implemented on its proto message
+pub trait Component {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error>;
+}
+
+// custom evaluation of a component, given its proto message
+pub type Evaluator = Box<dyn Fn(&yarrow::component::Value, &NodeArguments, &Context) -> Result<NodeEvaluation, Error> + Send + Sync>;
+
+// evaluations registered by name, which take precedence over the built-in components
+#[derive(Default)]
+pub struct Registry {
+    evaluators: HashMap<String, Evaluator>
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    // name is the component type as it appears in analysis.proto, such as "Mean",
+    // or the name carried by a Custom component
+    pub fn register(&mut self, name: &str, evaluator: Evaluator) {
+        self.evaluators.insert(name.to_owned(), evaluator);
+    }
+
+    pub fn evaluate(&self, value: &yarrow::component::Value, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        let name = match value {
+            yarrow::component::Value::Custom(x) => x.name.as_str(),
+            _ => yarrow_validator::components::get_component(value).name()
+        };
+        if let Some(evaluator) = self.evaluators.get(name) {
+            return evaluator(value, arguments, context);
+        }
+        match get_component(value) {
+            Some(component) => component.evaluate(arguments, context),
+            None => Err(Error::Unimplemented)
+        }
+    }
+}
+
+// evaluators registered by the host, shared by every release
+static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
 
-    let table = dataset.tables.get(&datasource.dataset_id).unwrap();
-    let data = match table.value.as_ref().unwrap() {
-        yarrow::table::Value::FilePath(path) => {
+pub fn get_registry() -> &'static RwLock<Registry> {
+    REGISTRY.get_or_init(|| RwLock::new(Registry::new()))
+}
 
-            fn get_column<T>(path: &String, column: &String) -> Vec<T>
-                where T: FromStr, <T as std::str::FromStr>::Err: std::fmt::Debug {
-                let mut rdr = csv::Reader::from_path(path).unwrap();
-                rdr.deserialize().map(|result| {
-                    let record: HashMap<String, String> = result.unwrap();
-//                    println!("{:?}", record);
-                    record[column].parse::<T>().unwrap()
-                }).collect()
-            }
+// register an evaluator for every subsequent release
+pub fn register(name: &str, evaluator: Evaluator) {
+    // the write lock is only held to insert an evaluator, so a poisoned registry is still consistent
+    get_registry().write().unwrap_or_else(|poisoned| poisoned.into_inner()).register(name, evaluator);
+}
 
-            match arguments.get("datatype").unwrap() {
-                FieldEvaluation::Str(x) => Ok(match x.first().unwrap().as_ref() {
+// built-in evaluation of a component, or None if the runtime cannot evaluate it
+pub fn get_component(value: &yarrow::component::Value) -> Option<&dyn Component> {
+    use yarrow::component::Value::*;
+    Some(match value {
+        Literal(x) => x,
+        Datasource(x) => x,
+        Add(x) => x,
+        Subtract(x) => x,
+        Divide(x) => x,
+        Multiply(x) => x,
+        Power(x) => x,
+        Negate(x) => x,
+        Bin(x) => x,
+        Count(x) => x,
+        Median(x) => x,
+        Sum(x) => x,
+        Dpmean(x) => x,
+        Dpvariance(x) => x,
+        Dpmomentraw(x) => x,
+        Dpcovariance(x) => x,
+        Exponentialmechanism(x) => x,
+        Constant(_) | Laplace(_) | Mean(_) | Clip(_) | Custom(_) => return None
+    })
+}
+
+impl Component for yarrow::Literal {
+    fn evaluate(&self, _arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match self.value.as_ref() {
+            Some(value) => Ok(hashmap!["data".to_owned() => parse_proto_array(value)?]),
+            None => Err(Error::Decode("Literal value must be set".to_string()))
+        }
+    }
+}
+
+impl Component for yarrow::DataSource {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        let table = context.dataset.tables.get(&self.dataset_id)
+            .ok_or_else(|| Error::Dataset(format!("dataset \"{}\" is not provided", self.dataset_id)))?;
+        let data = match table.value.as_ref() {
+            Some(yarrow::table::Value::FilePath(path)) => {
+
+                fn get_column<T>(path: &String, column: &String) -> Result<Vec<T>, Error>
+                    where T: FromStr {
+                    let mut rdr = csv::Reader::from_path(path)
+                        .map_err(|error| Error::Dataset(format!("unable to read {}: {}", path, error)))?;
+                    rdr.deserialize().map(|result| {
+                        let record: HashMap<String, String> = result
+                            .map_err(|error| Error::Dataset(format!("unable to read {}: {}", path, error)))?;
+//                        println!("{:?}", record);
+                        let value = record.get(column)
+                            .ok_or_else(|| Error::Dataset(format!("column \"{}\" is not in {}", column, path)))?;
+                        value.parse::<T>()
+                            .map_err(|_| Error::Dataset(format!("unable to parse \"{}\" in column \"{}\"", value, column)))
+                    }).collect()
+                }
+
+                match get_str(&arguments, "datatype")?.as_ref() {
                     "BYTES" =>
-                        Ok(FieldEvaluation::Bytes(Array1::from(get_column::<u8>(&path, &datasource.column_id)).into_dyn())),
+                        FieldEvaluation::Bytes(Array1::from(get_column::<u8>(&path, &self.column_id)?).into_dyn()),
                     "BOOL" =>
-                        Ok(FieldEvaluation::Bool(Array1::from(get_column::<bool>(&path, &datasource.column_id)).into_dyn())),
+                        FieldEvaluation::Bool(Array1::from(get_column::<bool>(&path, &self.column_id)?).into_dyn()),
                     "I64" =>
-                        Ok(FieldEvaluation::I64(Array1::from(get_column::<i64>(&path, &datasource.column_id)).into_dyn())),
+                        FieldEvaluation::I64(Array1::from(get_column::<i64>(&path, &self.column_id)?).into_dyn()),
                     "F64" =>
-                        Ok(FieldEvaluation::F64(Array1::from(get_column::<f64>(&path, &datasource.column_id)).into_dyn())),
+                        FieldEvaluation::F64(Array1::from(get_column::<f64>(&path, &self.column_id)?).into_dyn()),
                     "STRING" =>
-                        Ok(FieldEvaluation::Str(Array1::from(get_column::<String>(&path, &datasource.column_id)).into_dyn())),
-                    _ => Err("Datatype is not recognized.")
-                }.unwrap()),
-                _ => Err("Datatype must be a string.")
-            }
-        },
-        yarrow::table::Value::Literal(value) => Ok(parse_proto_array(&value)),
-        _ => Err("Only file paths are supported")
-    }.unwrap();
-
-    hashmap!["data".to_owned() => data]
-}
-
-pub fn component_add(_x: &yarrow::Add, arguments: &NodeArguments) -> NodeEvaluation {
-//    println!("add");
-    match (arguments.get("left").unwrap(), arguments.get("right").unwrap()) {
-        (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x + y)]),
-        (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x + y)]),
-        _ => Err("Add: Either the argument types are mismatched or non-numeric.")
-    }.unwrap()
-}
-
-
-pub fn component_subtract(_x: &yarrow::Subtract, arguments: &NodeArguments) -> NodeEvaluation {
-    match (arguments.get("left").unwrap(), arguments.get("right").unwrap()) {
-        (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x - y)]),
-        (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x - y)]),
-        _ => Err("Subtract: Either the argument types are mismatched or non-numeric.")
-    }.unwrap()
-}
-
-pub fn component_divide(_x: &yarrow::Divide, arguments: &NodeArguments) -> NodeEvaluation {
-    match (arguments.get("left").unwrap(), arguments.get("right").unwrap()) {
-        (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x / y)]),
-        (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x / y)]),
-        _ => Err("Divide: Either the argument types are mismatched or non-numeric.")
-    }.unwrap()
-}
-
-pub fn component_multiply(_x: &yarrow::Multiply, arguments: &NodeArguments) -> NodeEvaluation {
-    match (arguments.get("left").unwrap(), arguments.get("right").unwrap()) {
-        (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x * y)]),
-        (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x * y)]),
-        _ => Err("Multiply: Either the argument types are mismatched or non-numeric.")
-    }.unwrap()
-}
-
-pub fn component_power(_x: &yarrow::Power, arguments: &NodeArguments) -> NodeEvaluation {
-    let power: f64 = get_f64(&arguments, "right");
-    let data = get_array_f64(&arguments, "left");
-    hashmap!["data".to_string() => FieldEvaluation::F64(data.mapv(|x| x.powf(power)))]
-}
-
-pub fn component_negate(_x: &yarrow::Negate, arguments: &NodeArguments) -> NodeEvaluation {
-    match arguments.get("data").unwrap() {
-        FieldEvaluation::F64(x) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::F64(-x)]),
-        FieldEvaluation::I64(x) =>
-            Ok(hashmap!["data".to_string() => FieldEvaluation::I64(-x)]),
-        _ => Err("Negate: Argument must be numeric.")
-    }.unwrap()
-}
-
-pub fn component_bin(_X: &yarrow::Bin, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: ArrayD<f64> = get_array_f64(&arguments, "data");
-    let edges: ArrayD<f64> = get_array_f64(&arguments, "edges");
-    let inclusive_left: bool = get_bool(&arguments, "inclusive_left");
-    hashmap!["data".to_string() => FieldEvaluation::Str(utilities::transformations::bin(&data, &edges, &inclusive_left))]
-}
-
-pub fn component_count(_X: &yarrow::Count, arguments: &NodeArguments) -> NodeEvaluation {
-    match (arguments.get("data").unwrap(), arguments.get("group_by").unwrap()) {
-        (FieldEvaluation::F64(data), FieldEvaluation::F64(group_by)) => Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::count(&get_array_f64(&arguments, "data"), &Some(get_array_f64(&arguments, "group_by"))))]),
-        (FieldEvaluation::Str(data), FieldEvaluation::Str(group_by)) => Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::count(&get_array_str(&arguments, "data"), &Some(get_array_str(&arguments, "group_by"))))]),
-        (FieldEvaluation::Bool(data), FieldEvaluation::Bool(group_by)) => Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::count(&get_array_bool(&arguments, "data"), &Some(get_array_bool(&arguments, "group_by"))))]),
-        _ => Err("Count: Data type must be f64, string, or bool")
-    }.unwrap()
+                        FieldEvaluation::Str(Array1::from(get_column::<String>(&path, &self.column_id)?).into_dyn()),
+                    _ => return Err(Error::InvalidArgument("datatype".to_string(), "is not recognized".to_string()))
+                }
+            },
+            Some(yarrow::table::Value::Literal(value)) => parse_proto_array(&value)?,
+            _ => return Err(Error::Dataset("only file paths and literals are supported".to_string()))
+        };
+
+        Ok(hashmap!["data".to_owned() => data])
+    }
+}
+
+impl Component for yarrow::Add {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match (get_argument(&arguments, "left")?, get_argument(&arguments, "right")?) {
+            (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x + y)]),
+            (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x + y)]),
+            _ => Err(Error::InvalidArgument("right".to_string(), "must have the same numeric type as left".to_string()))
+        }
+    }
+}
+
+impl Component for yarrow::Subtract {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match (get_argument(&arguments, "left")?, get_argument(&arguments, "right")?) {
+            (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x - y)]),
+            (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x - y)]),
+            _ => Err(Error::InvalidArgument("right".to_string(), "must have the same numeric type as left".to_string()))
+        }
+    }
+}
+
+impl Component for yarrow::Divide {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match (get_argument(&arguments, "left")?, get_argument(&arguments, "right")?) {
+            (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x / y)]),
+            (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x / y)]),
+            _ => Err(Error::InvalidArgument("right".to_string(), "must have the same numeric type as left".to_string()))
+        }
+    }
+}
+
+impl Component for yarrow::Multiply {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match (get_argument(&arguments, "left")?, get_argument(&arguments, "right")?) {
+            (FieldEvaluation::F64(x), FieldEvaluation::F64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::F64(x * y)]),
+            (FieldEvaluation::I64(x), FieldEvaluation::I64(y)) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::I64(x * y)]),
+            _ => Err(Error::InvalidArgument("right".to_string(), "must have the same numeric type as left".to_string()))
+        }
+    }
+}
+
+impl Component for yarrow::Power {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        let power: f64 = get_f64(&arguments, "right")?;
+        let data = get_array_f64(&arguments, "left")?;
+        Ok(hashmap!["data".to_string() => FieldEvaluation::F64(data.mapv(|x| x.powf(power)))])
+    }
+}
+
+impl Component for yarrow::Negate {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match get_argument(&arguments, "data")? {
+            FieldEvaluation::F64(x) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::F64(-x)]),
+            FieldEvaluation::I64(x) =>
+                Ok(hashmap!["data".to_string() => FieldEvaluation::I64(-x)]),
+            _ => Err(Error::InvalidArgument("data".to_string(), "must be numeric".to_string()))
+        }
+    }
+}
+
+impl Component for yarrow::Bin {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        let data: ArrayD<f64> = get_array_f64(&arguments, "data")?;
+        let edges: ArrayD<f64> = get_array_f64(&arguments, "edges")?;
+        let inclusive_left: bool = get_bool(&arguments, "inclusive_left")?;
+        Ok(hashmap!["data".to_string() => FieldEvaluation::Str(utilities::transformations::bin(&data, &edges, &inclusive_left))])
+    }
+}
+
+impl Component for yarrow::Count {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        match (get_argument(&arguments, "data")?, get_argument(&arguments, "group_by")?) {
+            (FieldEvaluation::F64(data), FieldEvaluation::F64(group_by)) => Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::count(&get_array_f64(&arguments, "data")?, &Some(get_array_f64(&arguments, "group_by")?)))]),
+            (FieldEvaluation::Str(data), FieldEvaluation::Str(group_by)) => Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::count(&get_array_str(&arguments, "data")?, &Some(get_array_str(&arguments, "group_by")?)))]),
+            (FieldEvaluation::Bool(data), FieldEvaluation::Bool(group_by)) => Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::count(&get_array_bool(&arguments, "data")?, &Some(get_array_bool(&arguments, "group_by")?)))]),
+            _ => Err(Error::InvalidArgument("data".to_string(), "must be f64, string or bool, with the same type as group_by".to_string()))
+        }
+    }
 }
 
 // pub fn component_histogram(_X: &yarrow::Bin, argument: &NodeArguments) -> NodeEvaluation {
 //     // Christian TODO: Simple version here -- need to check with Mike
-//     let data: ArrayD<f64> = get_array_f64(&arguments, "data");
-//     let edges: ArrayD<f64> = get_array_f64(&arguments, "edges");
-//     let inclusive_left: bool = get_bool(&arguments, "inclusive_left");
+//     let data: ArrayD<f64> = get_array_f64(&arguments, "data")?;
+//     let edges: ArrayD<f64> = get_array_f64(&arguments, "edges")?;
+//     let inclusive_left: bool = get_bool(&arguments, "inclusive_left")?;
 //     hashmap!["data".to_string() => FieldEvaluation::HistHashMap(utilities::aggregations::histogram(&data, &edges, &inclusive_left))]
 // }
 
-pub fn component_median(_x: &yarrow::Median, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: ArrayD<f64> = get_array_f64(&arguments, "data");
-    hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::median(&data))]
-}
-
-pub fn component_sum(_x: &yarrow::Sum, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: ArrayD<f64> = get_array_f64(&arguments, "data");
-    hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::sum(&data))]
-}
-
-// TODO: Possibly compute sensitivity here, and pass into algorithm?
-
-pub fn component_dp_mean(component: &yarrow::DpMean, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: FieldEvaluation = match yarrow::Mechanism::from_i32(component.mechanism).unwrap() {
-        yarrow::Mechanism::Laplace => Ok(FieldEvaluation::F64(Array::from_elem((), algorithms::dp_mean_laplace(
-            component.epsilon,
-            get_f64(&arguments, "num_records"),
-            get_array_f64(&arguments, "data"),
-            get_f64(&arguments, "minimum"),
-            get_f64(&arguments, "maximum")
-        )).into_dyn())),
-        _ => Err("Mean: Unknown algorithm type.")
-    }.unwrap();
-    // println!("dpmean");
-    hashmap!["data".to_string() => data]
-}
-
-pub fn component_dp_variance(component: &yarrow::DpVariance, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: FieldEvaluation = match yarrow::Mechanism::from_i32(component.mechanism).unwrap() {
-        yarrow::Mechanism::Laplace => Ok(FieldEvaluation::F64(Array::from_elem((), algorithms::dp_variance_laplace(
-            component.epsilon,
-            get_f64(&arguments, "num_records"),
-            get_array_f64(&arguments, "data"),
-            get_f64(&arguments, "minimum"),
-            get_f64(&arguments, "maximum")
-        )).into_dyn())),
-        _ => Err("Variance: Unknown algorithm type.")
-    }.unwrap();
-    hashmap!["data".to_string() => data]
-}
-
-pub fn component_dp_moment_raw(component: &yarrow::DpMomentRaw, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: FieldEvaluation = match yarrow::Mechanism::from_i32(component.mechanism).unwrap() {
-        yarrow::Mechanism::Laplace => Ok(FieldEvaluation::F64(Array::from_elem((), algorithms::dp_moment_raw_laplace(
-            component.epsilon,
-            get_f64(&arguments, "num_records"),
-            get_array_f64(&arguments, "data"),
-            get_f64(&arguments, "minimum"),
-            get_f64(&arguments, "maximum"),
-            component.order
-        )).into_dyn())),
-        _ => Err("Moment Raw: Unknown algorithm type.")
-    }.unwrap();
-    hashmap!["data".to_string() => data]
-}
-
-
-pub fn component_dp_covariance(component: &yarrow::DpCovariance, arguments: &NodeArguments) -> NodeEvaluation {
-    let data: FieldEvaluation = match yarrow::Mechanism::from_i32(component.mechanism).unwrap() {
-        yarrow::Mechanism::Laplace => Ok(FieldEvaluation::F64(Array::from_elem((), algorithms::dp_covariance(
-            component.epsilon,
-            get_f64(&arguments, "num_records"),
-            get_array_f64(&arguments, "data_x"),
-            get_array_f64(&arguments, "data_y"),
-            get_f64(&arguments, "minimum_x"),
-            get_f64(&arguments, "minimum_y"),
-            get_f64(&arguments, "maximum_x"),
-            get_f64(&arguments, "maximum_y")
-        )).into_dyn())),
-        _ => Err("Covariance: Unknown algorithm type.")
-    }.unwrap();
-    hashmap!["data".to_string() => data]
+impl Component for yarrow::Median {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        let data: ArrayD<f64> = get_array_f64(&arguments, "data")?;
+        Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::median(&data))])
+    }
+}
+
+impl Component for yarrow::Sum {
+    fn evaluate(&self, arguments: &NodeArguments, _context: &Context) -> Result<NodeEvaluation, Error> {
+        let data: ArrayD<f64> = get_array_f64(&arguments, "data")?;
+        Ok(hashmap!["data".to_string() => FieldEvaluation::F64(utilities::aggregations::sum(&data))])
+    }
+}
+
+// sensitivities and Gaussian noise scales are shared with the validator, so that reported and applied noise agree
+use yarrow_validator::sensitivity;
+use yarrow_validator::privacy;
+use yarrow_validator::snapping;
+
+fn unsupported_mechanism(mechanism: i32) -> Error {
+    Error::UnsupportedMechanism(format!("{:?}", yarrow::Mechanism::from_i32(mechanism)))
+}
+
+// noise of the requested mechanism, calibrated to the sensitivity of the statistic
+fn get_noise(mechanism: i32, epsilon: f64, delta: f64, bound: f64, sensitivity: f64) -> Result<algorithms::Noise, Error> {
+    match yarrow::Mechanism::from_i32(mechanism) {
+        Some(yarrow::Mechanism::Laplace) => Ok(algorithms::Noise::Laplace {scale: sensitivity / epsilon}),
+        Some(yarrow::Mechanism::Gaussian) => Ok(algorithms::Noise::Gaussian {
+            scale: privacy::get_gaussian_scale(epsilon, delta) * sensitivity
+        }),
+        Some(yarrow::Mechanism::Snapping) => Ok(algorithms::Noise::Snapping {epsilon, bound, sensitivity}),
+        _ => Err(unsupported_mechanism(mechanism))
+    }
+}
+
+// the estimate, along with the effective epsilon of the noise when it is calibrated by the snapping mechanism
+fn dp_evaluation(estimate: f64, mechanism: i32, epsilon: f64, bound: f64, sensitivity: f64) -> NodeEvaluation {
+    let mut evaluation = hashmap!["data".to_string() => FieldEvaluation::F64(Array::from_elem((), estimate).into_dyn())];
+    if yarrow::Mechanism::from_i32(mechanism) == Some(yarrow::Mechanism::Snapping) {
+        let epsilon_prime = snapping::get_epsilon_prime(epsilon, bound, sensitivity);
+        evaluation.insert("epsilon_prime".to_string(), FieldEvaluation::F64(Array::from_elem((), epsilon_prime).into_dyn()));
+    }
+    evaluation
+}
+
+impl Component for yarrow::DpMean {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        let num_records = get_f64(arguments, "num_records")?;
+        let data = get_array_f64(arguments, "data")?;
+        let minimum = get_f64(arguments, "minimum")?;
+        let maximum = get_f64(arguments, "maximum")?;
+        let sensitivity = sensitivity::dp_mean(context.privacy_definition, num_records, minimum, maximum);
+        let bound = snapping::dp_mean_bound(minimum, maximum);
+
+        let estimate = algorithms::privatize(algorithms::mean(num_records, data, minimum, maximum),
+                                             &get_noise(self.mechanism, self.epsilon, self.delta, bound, sensitivity)?)?;
+        Ok(dp_evaluation(estimate, self.mechanism, self.epsilon, bound, sensitivity))
+    }
+}
+
+impl Component for yarrow::DpVariance {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        let num_records = get_f64(arguments, "num_records")?;
+        let data = get_array_f64(arguments, "data")?;
+        let minimum = get_f64(arguments, "minimum")?;
+        let maximum = get_f64(arguments, "maximum")?;
+        let sensitivity = sensitivity::dp_variance(context.privacy_definition, num_records, minimum, maximum);
+        let bound = snapping::dp_variance_bound(minimum, maximum);
+
+        let estimate = algorithms::privatize(algorithms::variance(num_records, data, minimum, maximum)?,
+                                             &get_noise(self.mechanism, self.epsilon, self.delta, bound, sensitivity)?)?;
+        Ok(dp_evaluation(estimate, self.mechanism, self.epsilon, bound, sensitivity))
+    }
+}
+
+impl Component for yarrow::DpMomentRaw {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        let num_records = get_f64(arguments, "num_records")?;
+        let data = get_array_f64(arguments, "data")?;
+        let minimum = get_f64(arguments, "minimum")?;
+        let maximum = get_f64(arguments, "maximum")?;
+        let sensitivity = sensitivity::dp_moment_raw(context.privacy_definition, num_records, minimum, maximum, self.order);
+        let bound = snapping::dp_moment_raw_bound(minimum, maximum, self.order);
+
+        let estimate = algorithms::privatize(algorithms::moment_raw(num_records, data, minimum, maximum, self.order),
+                                             &get_noise(self.mechanism, self.epsilon, self.delta, bound, sensitivity)?)?;
+        Ok(dp_evaluation(estimate, self.mechanism, self.epsilon, bound, sensitivity))
+    }
+}
+
+impl Component for yarrow::DpCovariance {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        let num_records = get_f64(arguments, "num_records")?;
+        let data_x = get_array_f64(arguments, "data_x")?;
+        let data_y = get_array_f64(arguments, "data_y")?;
+        let minimum_x = get_f64(arguments, "minimum_x")?;
+        let minimum_y = get_f64(arguments, "minimum_y")?;
+        let maximum_x = get_f64(arguments, "maximum_x")?;
+        let maximum_y = get_f64(arguments, "maximum_y")?;
+        let sensitivity = sensitivity::dp_covariance(context.privacy_definition, num_records,
+                                                     minimum_x, minimum_y, maximum_x, maximum_y);
+        let bound = snapping::dp_covariance_bound(minimum_x, minimum_y, maximum_x, maximum_y);
+
+        let estimate = algorithms::privatize(
+            algorithms::covariance(num_records, data_x, data_y, minimum_x, minimum_y, maximum_x, maximum_y)?,
+            &get_noise(self.mechanism, self.epsilon, self.delta, bound, sensitivity)?)?;
+        Ok(dp_evaluation(estimate, self.mechanism, self.epsilon, bound, sensitivity))
+    }
+}
+
+// count of records equal to the candidate
+fn count_utility<T: PartialEq>(data: &ArrayD<T>) -> impl Fn(&T) -> i64 + '_ {
+    move |candidate| data.iter().filter(|x| *x == candidate).count() as i64
+}
+
+// negative imbalance between the records below and above the candidate, which is greatest at the median
+fn median_utility<T: PartialOrd>(data: &ArrayD<T>) -> impl Fn(&T) -> i64 + '_ {
+    move |candidate| {
+        let below = data.iter().filter(|x| *x < candidate).count() as i64;
+        let above = data.iter().filter(|x| *x > candidate).count() as i64;
+        -(below - above).abs()
+    }
+}
+
+impl Component for yarrow::ExponentialMechanism {
+    fn evaluate(&self, arguments: &NodeArguments, context: &Context) -> Result<NodeEvaluation, Error> {
+        use yarrow::exponential_mechanism::Utility;
+        let utility = Utility::from_i32(self.utility)
+            .ok_or_else(|| Error::InvalidArgument("utility".to_string(), "is not recognized".to_string()))?;
+        let sensitivity = sensitivity::exponential_mechanism(context.privacy_definition, self.utility);
+
+        let data = get_argument(arguments, "data")?;
+        let candidates = get_argument(arguments, "candidates")?;
+        let is_empty = match candidates {
+            FieldEvaluation::Bytes(x) => x.is_empty(),
+            FieldEvaluation::Bool(x) => x.is_empty(),
+            FieldEvaluation::I64(x) => x.is_empty(),
+            FieldEvaluation::F64(x) => x.is_empty(),
+            FieldEvaluation::Str(x) => x.is_empty()
+        };
+        if is_empty {
+            return Err(Error::InvalidArgument("candidates".to_string(), "must not be empty".to_string()));
+        }
+
+        let selected = match (utility, data, candidates) {
+            (Utility::Count, FieldEvaluation::F64(data), FieldEvaluation::F64(candidates)) => FieldEvaluation::F64(arr0(
+                algorithms::dp_exponential(self.epsilon, candidates.clone(), &count_utility(data), sensitivity)?).into_dyn()),
+            (Utility::Count, FieldEvaluation::I64(data), FieldEvaluation::I64(candidates)) => FieldEvaluation::I64(arr0(
+                algorithms::dp_exponential(self.epsilon, candidates.clone(), &count_utility(data), sensitivity)?).into_dyn()),
+            (Utility::Count, FieldEvaluation::Str(data), FieldEvaluation::Str(candidates)) => FieldEvaluation::Str(arr0(
+                algorithms::dp_exponential(self.epsilon, candidates.clone(), &count_utility(data), sensitivity)?).into_dyn()),
+            (Utility::Count, FieldEvaluation::Bool(data), FieldEvaluation::Bool(candidates)) => FieldEvaluation::Bool(arr0(
+                algorithms::dp_exponential(self.epsilon, candidates.clone(), &count_utility(data), sensitivity)?).into_dyn()),
+            (Utility::Median, FieldEvaluation::F64(data), FieldEvaluation::F64(candidates)) => FieldEvaluation::F64(arr0(
+                algorithms::dp_exponential(self.epsilon, candidates.clone(), &median_utility(data), sensitivity)?).into_dyn()),
+            (Utility::Median, FieldEvaluation::I64(data), FieldEvaluation::I64(candidates)) => FieldEvaluation::I64(arr0(
+                algorithms::dp_exponential(self.epsilon, candidates.clone(), &median_utility(data), sensitivity)?).into_dyn()),
+            (Utility::Count, _, _) => return Err(Error::InvalidArgument(
+                "candidates".to_string(), "must be f64, i64, string or bool, with the same type as data".to_string())),
+            (Utility::Median, _, _) => return Err(Error::InvalidArgument(
+                "candidates".to_string(), "must be numeric, with the same type as data".to_string()))
+        };
+        Ok(hashmap!["data".to_string() => selected])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(registry: &Registry, value: &yarrow::component::Value, data: &FieldEvaluation) -> Result<NodeEvaluation, Error> {
+        let dataset = yarrow::Dataset::default();
+        let privacy_definition = yarrow::PrivacyDefinition::default();
+        let context = Context {dataset: &dataset, privacy_definition: &privacy_definition};
+        registry.evaluate(value, &hashmap!["data".to_string() => data], &context)
+    }
+
+    #[test]
+    fn unimplemented_components_are_reported() {
+        let data = FieldEvaluation::F64(arr1(&[1., 2., 3.]).into_dyn());
+        let mean = yarrow::component::Value::Mean(yarrow::Mean {});
+        assert!(matches!(evaluate(&Registry::new(), &mean, &data), Err(Error::Unimplemented)));
+    }
+
+    #[test]
+    fn registered_evaluators_take_precedence() {
+        let mut registry = Registry::new();
+        registry.register("Mean", Box::new(|_value, arguments, _context| {
+            let data = get_array_f64(arguments, "data")?;
+            Ok(hashmap!["data".to_string() => FieldEvaluation::F64(arr0(data.sum() / data.len() as f64).into_dyn())])
+        }));
+        registry.register("Sum", Box::new(|_value, _arguments, _context| Err(Error::Unimplemented)));
+
+        let data = FieldEvaluation::F64(arr1(&[1., 2., 3.]).into_dyn());
+        let mean = yarrow::component::Value::Mean(yarrow::Mean {});
+        match &evaluate(&registry, &mean, &data).unwrap()["data"] {
+            FieldEvaluation::F64(value) => assert_eq!(value.sum(), 2.),
+            _ => panic!("mean must be a float")
+        }
+        let sum = yarrow::component::Value::Sum(yarrow::Sum {});
+        assert!(matches!(evaluate(&registry, &sum, &data), Err(Error::Unimplemented)));
+    }
+
+    #[test]
+    fn noise_is_calibrated_to_the_sensitivity() {
+        match get_noise(yarrow::Mechanism::Laplace as i32, 0.5, 0., 10., 2.) {
+            Ok(algorithms::Noise::Laplace {scale}) => assert_eq!(scale, 4.),
+            _ => panic!("Laplace noise must be calibrated")
+        }
+        match get_noise(yarrow::Mechanism::Gaussian as i32, 1., 1e-6, 10., 2.) {
+            Ok(algorithms::Noise::Gaussian {scale}) => assert_eq!(scale, privacy::get_gaussian_scale(1., 1e-6) * 2.),
+            _ => panic!("Gaussian noise must be calibrated")
+        }
+        assert!(matches!(get_noise(99, 1., 0., 10., 2.), Err(Error::UnsupportedMechanism(_))));
+    }
+
+    #[test]
+    fn snapping_reports_effective_epsilon() {
+        let data = FieldEvaluation::F64(arr1(&[1., 2., 3., 4.]).into_dyn());
+        let (minimum, maximum, num_records) = (
+            FieldEvaluation::F64(arr0(0.).into_dyn()),
+            FieldEvaluation::F64(arr0(10.).into_dyn()),
+            FieldEvaluation::I64(arr0(4).into_dyn()));
+        let arguments: NodeArguments = hashmap![
+            "data".to_string() => &data, "minimum".to_string() => &minimum,
+            "maximum".to_string() => &maximum, "num_records".to_string() => &num_records];
+        let dataset = yarrow::Dataset::default();
+        let privacy_definition = yarrow::PrivacyDefinition::default();
+        let context = Context {dataset: &dataset, privacy_definition: &privacy_definition};
+
+        let dp_mean = yarrow::DpMean {epsilon: 1., mechanism: yarrow::Mechanism::Snapping as i32, ..Default::default()};
+        let evaluation = dp_mean.evaluate(&arguments, &context).unwrap();
+        match &evaluation["epsilon_prime"] {
+            FieldEvaluation::F64(epsilon_prime) => {
+                let sensitivity = sensitivity::dp_mean(&privacy_definition, 4., 0., 10.);
+                assert_eq!(epsilon_prime.sum(), snapping::get_epsilon_prime(1., 10., sensitivity));
+                assert!(epsilon_prime.sum() <= 1.);
+            },
+            _ => panic!("epsilon_prime must be a float")
+        }
+        assert!(evaluation.contains_key("data"));
+    }
 }
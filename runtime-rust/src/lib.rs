@@ -1,9 +1,9 @@
 extern crate yarrow_validator;
 use yarrow_validator::yarrow;
 
-mod base;
+pub mod base;
 mod utilities;
-mod components;
+pub mod components;
 mod algorithms;
 
 use ndarray::prelude::*;
@@ -26,13 +26,94 @@ unsafe fn get_buffer<'a>(data: *const u8, len: i32) -> &'a [u8] {
     }
 }
 
+// buffer allocated by the host, which the runtime hands back to the host to free
 #[repr(C)]
-#[allow(dead_code)]
-struct ByteBuffer {
+pub struct ByteBuffer {
     len: i64,
     data: *mut u8,
 }
 
+fn decode<T: prost::Message + Default>(buffer: &[u8], name: &str) -> Result<T, yarrow::Error> {
+    prost::Message::decode(buffer).map_err(|error| yarrow::Error {
+        message: format!("unable to decode {}: {}", name, error),
+        ..Default::default()
+    })
+}
+
+fn compute_release(
+    dataset_buffer: &[u8], analysis_buffer: &[u8], release_buffer: &[u8]
+) -> Result<yarrow::Release, yarrow::Error> {
+
+    let dataset: yarrow::Dataset = decode(dataset_buffer, "dataset")?;
+    let analysis: yarrow::Analysis = decode(analysis_buffer, "analysis")?;
+    let release: yarrow::Release = decode(release_buffer, "release")?;
+
+    // refuse to run analyses that are malformed or exceed their declared privacy budget
+    let errors: Vec<yarrow::Diagnostic> = yarrow_validator::base::validate_analysis(&analysis)
+        .into_iter().filter(yarrow_validator::base::is_error).collect();
+    if let Some(first) = errors.first() {
+        return Err(yarrow::Error {
+            node_id: first.node_id,
+            component: base::get_component_name(analysis.graph.get(&first.node_id)),
+            message: format!("analysis failed validation, release refused:\n{}", errors.iter()
+                .map(|error| error.message.to_owned()).collect::<Vec<String>>().join("\n"))
+        });
+    }
+
+    let registry = components::get_registry().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    execute_graph(&analysis, &release, &dataset, &registry).map_err(yarrow::Error::from)
+}
+
+// evaluate a custom component on the host, given a serialized ReleaseNode of the arguments,
+// returning a serialized ReleaseNode of the outputs, or a null buffer if the evaluation failed
+pub type EvaluateCallback = extern "C" fn(arguments_ptr: *const u8, arguments_length: i32) -> ByteBuffer;
+// free a buffer returned by the evaluate callback
+pub type DestroyCallback = extern "C" fn(buffer: ByteBuffer);
+
+fn evaluate_on_host(name: &str, evaluate: EvaluateCallback, destroy: DestroyCallback,
+                    arguments: &base::NodeArguments) -> Result<base::NodeEvaluation, base::Error> {
+    let arguments = yarrow::ReleaseNode {
+        values: arguments.iter()
+            .map(|(name, evaluation)| (name.to_owned(), base::serialize_proto_array(evaluation)))
+            .collect()
+    };
+    let mut arguments_buffer = Vec::new();
+    prost::Message::encode(&arguments, &mut arguments_buffer)
+        .map_err(|error| base::Error::Decode(error.to_string()))?;
+
+    let output = evaluate(arguments_buffer.as_ptr(), arguments_buffer.len() as i32);
+    if output.data.is_null() {
+        return Err(base::Error::Custom(format!("the host failed to evaluate {}", name)));
+    }
+    // copy the outputs out of the host buffer before the host frees it
+    let output_buffer = unsafe {std::slice::from_raw_parts(output.data, output.len as usize)}.to_vec();
+    destroy(output);
+
+    let outputs: yarrow::ReleaseNode = prost::Message::decode(output_buffer.as_slice())
+        .map_err(|error| base::Error::Decode(error.to_string()))?;
+    outputs.values.iter()
+        .map(|(name, value)| Ok((name.to_owned(), base::parse_proto_array(value)?)))
+        .collect()
+}
+
+// evaluate Custom components with the given name by calling back into the host
+#[no_mangle]
+pub extern "C" fn register_component(
+    name: *const c_char, evaluate: EvaluateCallback, destroy: DestroyCallback
+) -> bool {
+    if name.is_null() {
+        return false;
+    }
+    let name = match unsafe {std::ffi::CStr::from_ptr(name)}.to_str() {
+        Ok(name) => name.to_owned(),
+        Err(_) => return false
+    };
+    let evaluator_name = name.clone();
+    components::register(&name, Box::new(move |_value, arguments, _context|
+        evaluate_on_host(&evaluator_name, evaluate, destroy, arguments)));
+    true
+}
+
 #[no_mangle]
 pub extern "C" fn release(
     dataset_ptr: *const u8, dataset_length: i32,
@@ -40,19 +121,27 @@ pub extern "C" fn release(
     release_ptr: *const u8, release_length: i32
 ) -> ffi_support::ByteBuffer {
 
-    let dataset_buffer = unsafe {get_buffer(dataset_ptr, dataset_length)};
-    let dataset: yarrow::Dataset = prost::Message::decode(dataset_buffer).unwrap();
+    // failures are returned as errors, so this only keeps a bug from unwinding into the host process
+    let result = std::panic::catch_unwind(|| compute_release(
+        unsafe {get_buffer(dataset_ptr, dataset_length)},
+        unsafe {get_buffer(analysis_ptr, analysis_length)},
+        unsafe {get_buffer(release_ptr, release_length)}));
 
-    let analysis_buffer = unsafe {get_buffer(analysis_ptr, analysis_length)};
-    let analysis: yarrow::Analysis = prost::Message::decode(analysis_buffer).unwrap();
-
-    let release_buffer = unsafe {get_buffer(release_ptr, release_length)};
-    let release: yarrow::Release = prost::Message::decode(release_buffer).unwrap();
-
-    let response_release = execute_graph(&analysis, &release, &dataset);
+    let response = yarrow::ReleaseResponse {
+        value: Some(match result {
+            Ok(Ok(release)) => yarrow::release_response::Value::Release(release),
+            Ok(Err(error)) => yarrow::release_response::Value::Error(error),
+            Err(panic) => yarrow::release_response::Value::Error(yarrow::Error {
+                message: panic.downcast_ref::<String>().cloned()
+                    .or_else(|| panic.downcast_ref::<&str>().map(|message| message.to_string()))
+                    .unwrap_or_else(|| "the runtime panicked".to_string()),
+                ..Default::default()
+            })
+        })
+    };
 
     let mut out_buffer = Vec::new();
-    match prost::Message::encode(&response_release, &mut out_buffer) {
+    match prost::Message::encode(&response, &mut out_buffer) {
         Ok(_t) => ffi_support::ByteBuffer::from_vec(out_buffer),
         Err(error) => {
             println!("Error encoding response protobuf.");
@@ -64,3 +153,129 @@ pub extern "C" fn release(
 
 //ffi_support::implement_into_ffi_by_protobuf!(yarrow::Release);
 ffi_support::define_bytebuffer_destructor!(dp_runtime_destroy_bytebuffer);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dp_mean(epsilon: f64) -> yarrow::Component {
+        yarrow::Component {
+            arguments: vec![("data".to_string(), yarrow::component::Field {
+                source_node_id: 1, source_field: "data".to_string()
+            })].into_iter().collect(),
+            value: Some(yarrow::component::Value::Dpmean(yarrow::DpMean {epsilon, ..Default::default()})),
+            ..Default::default()
+        }
+    }
+
+    fn encode<T: prost::Message>(message: &T) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        message.encode(&mut buffer).unwrap();
+        buffer
+    }
+
+    // call the runtime through the same interface as the bindings
+    fn call_release(dataset: &[u8], analysis: &[u8], release_buffer: &[u8]) -> yarrow::ReleaseResponse {
+        let response = release(
+            dataset.as_ptr(), dataset.len() as i32,
+            analysis.as_ptr(), analysis.len() as i32,
+            release_buffer.as_ptr(), release_buffer.len() as i32);
+        prost::Message::decode(response.into_vec().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn invalid_analysis_is_refused() {
+        let analysis = yarrow::Analysis {
+            graph: vec![(2, dp_mean(0.5))].into_iter().collect(),
+            privacy_definition: Some(yarrow::PrivacyDefinition {epsilon: 1., ..Default::default()})
+        };
+
+        let response = call_release(&encode(&yarrow::Dataset::default()), &encode(&analysis), &encode(&yarrow::Release::default()));
+        match response.value {
+            Some(yarrow::release_response::Value::Error(error)) => {
+                assert_eq!((error.node_id, error.component.as_str()), (2, "DPMean"));
+                assert!(error.message.starts_with("analysis failed validation, release refused"));
+            },
+            _ => panic!("the release must be refused")
+        }
+    }
+
+    #[test]
+    fn malformed_buffers_are_reported() {
+        let response = call_release(&encode(&yarrow::Dataset::default()), &[0xff], &encode(&yarrow::Release::default()));
+        match response.value {
+            Some(yarrow::release_response::Value::Error(error)) =>
+                assert!(error.message.starts_with("unable to decode analysis")),
+            _ => panic!("the analysis must fail to decode")
+        }
+    }
+
+    fn custom_analysis(name: &str) -> yarrow::Analysis {
+        let literal = yarrow::Literal {value: Some(yarrow::ArrayNd {
+            datatype: yarrow::DataType::F64 as i32,
+            data: Some(yarrow::array_nd::Data::F64(yarrow::Array1Df64 {data: vec![1., 2.]})),
+            order: vec![0],
+            shape: vec![2]
+        })};
+        yarrow::Analysis {
+            graph: vec![
+                (1, yarrow::Component {value: Some(yarrow::component::Value::Literal(literal)), ..Default::default()}),
+                (2, yarrow::Component {
+                    arguments: vec![("data".to_string(), yarrow::component::Field {
+                        source_node_id: 1, source_field: "data".to_string()
+                    })].into_iter().collect(),
+                    value: Some(yarrow::component::Value::Custom(yarrow::Custom {name: name.to_string()}))
+                })
+            ].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    // stands in for the host, doubling the data argument into a buffer the host owns
+    extern "C" fn double(arguments_ptr: *const u8, arguments_length: i32) -> ByteBuffer {
+        let arguments: yarrow::ReleaseNode = prost::Message::decode(
+            unsafe {get_buffer(arguments_ptr, arguments_length)}).unwrap();
+        let mut data = arguments.values["data"].clone();
+        if let Some(yarrow::array_nd::Data::F64(x)) = data.data.as_mut() {
+            x.data.iter_mut().for_each(|value| *value *= 2.);
+        }
+        let output = encode(&yarrow::ReleaseNode {values: vec![("data".to_string(), data)].into_iter().collect()});
+        let output = Box::into_raw(output.into_boxed_slice());
+        ByteBuffer {len: output.len() as i64, data: output as *mut u8}
+    }
+
+    extern "C" fn fail(_arguments_ptr: *const u8, _arguments_length: i32) -> ByteBuffer {
+        ByteBuffer {len: 0, data: std::ptr::null_mut()}
+    }
+
+    extern "C" fn destroy(buffer: ByteBuffer) {
+        unsafe {drop(Box::from_raw(std::slice::from_raw_parts_mut(buffer.data, buffer.len as usize)))}
+    }
+
+    #[test]
+    fn custom_components_are_evaluated_by_the_host() {
+        assert!(register_component(b"Double\0".as_ptr() as *const c_char, double, destroy));
+
+        let response = call_release(&encode(&yarrow::Dataset::default()), &encode(&custom_analysis("Double")), &encode(&yarrow::Release::default()));
+        match response.value {
+            Some(yarrow::release_response::Value::Release(release)) =>
+                assert_eq!(release.values[&2].values["data"].data, Some(yarrow::array_nd::Data::F64(yarrow::Array1Df64 {data: vec![2., 4.]}))),
+            _ => panic!("the custom component must be evaluated")
+        }
+    }
+
+    #[test]
+    fn custom_component_failures_are_reported() {
+        assert!(register_component(b"Fail\0".as_ptr() as *const c_char, fail, destroy));
+
+        let unregistered = call_release(&encode(&yarrow::Dataset::default()), &encode(&custom_analysis("Unregistered")), &encode(&yarrow::Release::default()));
+        let failed = call_release(&encode(&yarrow::Dataset::default()), &encode(&custom_analysis("Fail")), &encode(&yarrow::Release::default()));
+        match (unregistered.value, failed.value) {
+            (Some(yarrow::release_response::Value::Error(unregistered)), Some(yarrow::release_response::Value::Error(failed))) => {
+                assert_eq!((unregistered.node_id, unregistered.component.as_str()), (2, "Unregistered"));
+                assert_eq!((failed.node_id, failed.message.as_str()), (2, "the host failed to evaluate Fail"));
+            },
+            _ => panic!("the release must fail")
+        }
+    }
+}
@@ -6,6 +6,7 @@ use num;
 use rug;
 
 use crate::utilities::snapping;
+use crate::base::Error;
 
 pub fn sample_laplace(shift: f64, scale: f64) -> f64 {
     let probability: f64 = sample_uniform(0., 1.);
@@ -24,6 +25,21 @@ pub fn sample_uniform(min: f64, max: f64) -> f64 {
     (LittleEndian::read_u64(&buf) as f64) / (std::u64::MAX as f64) * (max - min) + min
 }
 
+// exact uniform sample from the integers in [0, upper), by rejecting samples of the same bit length
+pub fn sample_uniform_integer(upper: &rug::Integer) -> rug::Integer {
+    let bits = upper.significant_bits();
+    let mut buf = vec![0_u8; (bits as usize).div_ceil(8)];
+    loop {
+        rand_bytes(&mut buf).unwrap();
+        let hex: String = buf.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let mut sample = rug::Integer::from_str_radix(&hex, 16).unwrap_or_default();
+        sample.keep_bits_mut(bits);
+        if sample < *upper {
+            return sample;
+        }
+    }
+}
+
 pub fn sample_uniform_snapping() -> f64 {
     /// Returns random sample from Uniform(0,1)
     ///
@@ -57,7 +73,7 @@ pub fn sample_uniform_snapping() -> f64 {
     return uniform_rand;
 }
 
-pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sensitivity: &f64, precision: &f64) -> f64 {
+pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sensitivity: &f64, precision: &f64) -> Result<f64, Error> {
     /// Get noise according to the snapping mechanism
     ///
     /// # Arguments
@@ -68,7 +84,10 @@ pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sens
     /// * `precision` - amount of arithmetic precision to which we have access
     ///
     /// # Returns
-    /// noise according to snapping mechanism
+    /// noise according to snapping mechanism, such that the noisy statistic lies within [-B, B]
+    ///
+    /// # Errors
+    /// * the precision is not supported by the platform
     ///
     /// # Example
     /// ```
@@ -77,7 +96,7 @@ pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sens
     /// let B: f64 = 100.0;
     /// let sensitivity: f64 = 1.0/1000.0;
     /// let precision: f64 = 64.0;
-    /// let snapping_noise = sampling_snapping_noise(&mechanism_input, &epsilon, &B, &sensitivity, &precision);
+    /// let snapping_noise = sample_snapping_noise(&mechanism_input, &epsilon, &B, &sensitivity, &precision).unwrap();
     /// println!("snapping noise: {}", snapping_noise);
     /// ```
 
@@ -85,7 +104,7 @@ pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sens
     let u32_precision = *precision as u32;
     let u32_precision = std::cmp::min(u32_precision, 118_u32);
     if u32_precision > rug::float::prec_max() {
-        panic!("Operating system does not support sufficient precision to use the Snapping Mechanism");
+        return Err(Error::UnsupportedMechanism(format!("Snapping with {} bits of precision", u32_precision)));
     }
 
     // scale mechanism input by sensitivity
@@ -95,8 +114,7 @@ pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sens
     let (B_scaled, epsilon_prime, Lambda_prime, Lambda_prime_scaled, m) = snapping::parameter_setup(&epsilon, &B, &sensitivity, &precision);
 
     // generate random sign and draw from Unif(0,1)
-    let bit:i64 = snapping::get_bytes(1)[0..1].parse().unwrap();
-    let sign = (2*bit-1) as f64;
+    let sign = if snapping::get_bytes(1).starts_with('1') {1.} else {-1.};
     let u_star_sample = sample_uniform_snapping();
 
     // clamp to get inner result
@@ -106,10 +124,25 @@ pub fn sample_snapping_noise(mechanism_input: &f64, epsilon: &f64, B: &f64, sens
     let inner_result:f64 = num::clamp(mechanism_input_scaled, -B_scaled.abs(), B_scaled.abs()) +
                            (sign_precise * scale_precise * log_unif_precise).to_f64();
 
-    // perform rounding and snapping
+    // perform rounding and snapping, clamping in the units of the statistic rather than the scaled units
     let inner_result_rounded = snapping::get_closest_multiple_of_Lambda(&inner_result, &m);
-    let private_estimate = num::clamp(sensitivity * inner_result_rounded, -B_scaled.abs(), B_scaled.abs());
+    let private_estimate = num::clamp(sensitivity * inner_result_rounded, -B.abs(), B.abs());
     let snapping_mech_noise = private_estimate - mechanism_input;
 
-    return snapping_mech_noise;
-}
\ No newline at end of file
+    Ok(snapping_mech_noise)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapping_is_within_the_bound() {
+        let (epsilon, bound, sensitivity) = (1., 10., 0.25);
+        for input in [-1e6, -10., 3., 10., 1e6].iter() {
+            for _ in 0..50 {
+                let noise = sample_snapping_noise(input, &epsilon, &bound, &sensitivity, &yarrow_validator::snapping::PRECISION).unwrap();
+                assert!((input + noise).abs() <= bound, "{} + {} exceeds the bound", input, noise);
+            }
+        }
+    }
+}
@@ -229,7 +229,6 @@ pub fn round_components_to_nearest_int(sign: &str, exponent: &str, mantissa: &st
     let unbiased_exponent_numeric:usize = if unbiased_exponent_numeric_i64 > 0 { usize::try_from(unbiased_exponent_numeric_i64).unwrap()} else { 0 };
 
     // let unbiased_exponent_numeric = usize::try_from(unbiased_exponent_numeric_i64).unwrap();
-    println!("unbiased exponent numeric: {}", unbiased_exponent_numeric);
 
     // build strings of all zeros and ones to be used later
     let mut all_zeros = String::with_capacity(unbiased_exponent_numeric);
@@ -245,19 +244,16 @@ pub fn round_components_to_nearest_int(sign: &str, exponent: &str, mantissa: &st
     } else if unbiased_exponent_numeric_i64 >= 0 {
         // get elements of mantissa that represent integers (after being multiplied by 2^unbiased_exponent_num)
         let mantissa_subset:String = mantissa[0..unbiased_exponent_numeric].into();
-        println!("mantissa_subset: {}", mantissa_subset);
 
         // check to see if mantissa needs to be rounded up or down
         // if mantissa needs to be rounded up ...
         if mantissa[unbiased_exponent_numeric..unbiased_exponent_numeric+1] == *"1" {
             // if integer part of mantissa is all 1s, rounding needs to be reflected in the exponent instead
             if mantissa_subset == all_ones {
-                println!("rounding up exponent");
                 let exponent_increased_numeric = i64::from_str_radix(&exponent, 2).unwrap() + 1;
                 let exponent_increased_bin = format!("{:011b}", exponent_increased_numeric);
                 return(sign.to_string(), exponent_increased_bin.to_string(), format!("{:0<52}", "0"));
             } else {
-                println!("rounding up mantissa");
                 // if integer part of mantissa not all 1s, just increment mantissa
                 let mantissa_subset_increased_numeric = u64::from_str_radix(&mantissa_subset, 2).unwrap() + 1;
                 let mantissa_subset_increased_bin = format!("{:0>width$b}", mantissa_subset_increased_numeric, width = unbiased_exponent_numeric);
@@ -266,7 +262,6 @@ pub fn round_components_to_nearest_int(sign: &str, exponent: &str, mantissa: &st
             }
         } else {
             // mantissa needs to be rounded down
-            println!("rounding down mantissa");
             return(sign.to_string(), exponent.to_string(), format!("{:0<52}", mantissa_subset));
         }
     } else {
@@ -274,12 +269,10 @@ pub fn round_components_to_nearest_int(sign: &str, exponent: &str, mantissa: &st
         // let unbiased_exponent_numeric_i64 = unbiased_exponent_numeric as i64;
         if unbiased_exponent_numeric_i64 == -1 {
             // round int to +- 1
-            println!("rounding to +- 1");
             let exponent_for_one = format!("{:1<11}", "0");
             return(sign.to_string(), exponent_for_one.to_string(), format!("{:0<52}", "0"));
         } else {
             // round int to 0
-            println!("rounding to 0");
             let exponent_for_zero = format!("{:0>11}", "0");
             return(sign.to_string(), exponent_for_zero.to_string(), format!("{:0<52}", "0"));
         }
@@ -305,59 +298,8 @@ pub fn get_closest_multiple_of_Lambda(x: &f64, m: &i64) -> f64 {
     return Lambda_mult_f64;
 }
 
-pub fn redefine_epsilon(epsilon: &f64, B: &f64, precision: &f64) -> f64 {
-    /// Redefine epsilon for snapping mechanism such that we can
-    /// ensure that we do not exhaust too much privacy budget
-    ///
-    /// # Arguments
-    /// * `epsilon` - desired privacy guarantee
-    /// * `B` - snapping bound
-    /// * `precision` - amount of arithmetic precision to which we have access
-    ///
-    /// # Returns
-    /// functional epsilon that will determine amount of noise
-
-    let eta = 2_f64.powf(-precision);
-    return (epsilon - 2.0*eta) / (1.0 + 12.0*B*eta);
-}
-
-pub fn get_accuracy(alpha: &f64, epsilon: &f64, sensitivity: &f64, B: &f64, precision: &f64) -> f64 {
-    /// Get accuracy as described in
-    /// https://github.com/ctcovington/floating_point/blob/master/snapping_mechanism/notes/snapping_implementation_notes.pdf
-    ///
-    /// # Arguments
-    /// * `alpha` - desired confidence level
-    /// * `epsilon` - desired privacy guarantee
-    /// * `sensitivity` - sensitivity for function to which mechanism is being applied
-    /// * `B` - snapping bound
-    /// * `precision` - amount of arithmetic precision to which we have access
-    ///
-    /// # Returns
-    /// accuracy guarantee for snapping mechanism
-
-    let accuracy = ( (1.0 + 12.0 * B * 2_f64.powf(-precision)) / (epsilon - 2_f64.powf(-precision + 1.0)) )
-                   * (1.0 + (1.0 / alpha).ln()) * (sensitivity);
-    return accuracy;
-}
-
-pub fn get_epsilon(accuracy: &f64, alpha: &f64, sensitivity: &f64, B: &f64, precision: &f64) -> f64 {
-    /// Given accuracy, get epsilon as described in
-    /// https://github.com/ctcovington/floating_point/blob/master/snapping_mechanism/notes/snapping_implementation_notes.pdf
-    ///
-    /// # Arguments
-    /// * `accuracy` - desired accuracy level
-    /// * `alpha` - desired confidence level
-    /// * `sensitivity` - sensitivity for function to which mechanism is being applied
-    /// * `B` - snapping bound
-    /// * `precision` - amount of arithmetic precision to which we have access
-    ///
-    /// # Returns
-    /// epsilon use for snapping mechanism
-
-    let epsilon = ( (1.0 + 12.0 * B *2_f64.powf(-precision)) / accuracy) * (1.0 + (1.0 / alpha).ln())
-                  * (sensitivity) + 2_f64.powf(-precision + 1.0);
-    return epsilon;
-}
+// redefine_epsilon, get_accuracy and get_epsilon are shared with the validator, which accounts for the snapping mechanism
+use yarrow_validator::snapping::redefine_epsilon;
 
 pub fn parameter_setup(epsilon: &f64, B: &f64, sensitivity: &f64, precision: &f64) -> (f64, f64, f64, f64, i64) {
     /// Given input parameters, finds values of parameters for use inside of mechanism
@@ -373,7 +315,7 @@ pub fn parameter_setup(epsilon: &f64, B: &f64, sensitivity: &f64, precision: &f6
 
     // scale clamping bound by sensitivity
     let B_scaled = B / sensitivity;
-    let epsilon_prime = redefine_epsilon(&epsilon, &B_scaled, &precision);
+    let epsilon_prime = redefine_epsilon(*epsilon, B_scaled, *precision);
 
     // NOTE: this Lambda is calculated relative to lambda = 1/epsilon' rather than sensitivity/epsilon' because we have already
     //       scaled by the sensitivity
@@ -382,4 +324,31 @@ pub fn parameter_setup(epsilon: &f64, B: &f64, sensitivity: &f64, precision: &f6
     let Lambda_prime = Lambda_prime_scaled * sensitivity;
 
     return(B_scaled, epsilon_prime, Lambda_prime, Lambda_prime_scaled, m);
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_of_two_at_or_above() {
+        assert_eq!(get_smallest_greater_or_eq_power_of_two(&3.), (4., 2));
+        assert_eq!(get_smallest_greater_or_eq_power_of_two(&4.), (4., 2));
+        assert_eq!(get_smallest_greater_or_eq_power_of_two(&0.3), (0.5, -1));
+    }
+
+    #[test]
+    fn rounds_to_multiples_of_lambda() {
+        assert_eq!(get_closest_multiple_of_Lambda(&5.3, &0), 5.);
+        assert_eq!(get_closest_multiple_of_Lambda(&5.3, &1), 6.);
+        assert_eq!(get_closest_multiple_of_Lambda(&5.3, &-1), 5.5);
+        assert_eq!(get_closest_multiple_of_Lambda(&-5.3, &0), -5.);
+        assert_eq!(get_closest_multiple_of_Lambda(&0.3, &0), 0.);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        for x in &[0.1, -2.5, 1e300, 3.] {
+            assert_eq!(binary_to_f64(&f64_to_binary(x)), *x);
+        }
+    }
+}
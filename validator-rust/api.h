@@ -32,7 +32,9 @@ ByteBuffer from_accuracy(const uint8_t *analysis_ptr,
 ByteBuffer generate_report(const uint8_t *analysis_ptr,
                            int32_t analysis_length,
                            const uint8_t *release_ptr,
-                           int32_t release_length);
+                           int32_t release_length,
+                           const uint8_t *dataset_ptr,
+                           int32_t dataset_length);
 
 ByteBuffer infer_constraints(const uint8_t *analysis_ptr,
                              int32_t analysis_length,
@@ -44,6 +46,7 @@ ByteBuffer infer_constraints(const uint8_t *analysis_ptr,
 ByteBuffer to_accuracy(const uint8_t *analysis_ptr,
                        int32_t analysis_length,
                        const uint8_t *release_ptr,
-                       int32_t release_length);
+                       int32_t release_length,
+                       double alpha);
 
 ByteBuffer validate_analysis(const uint8_t *analysis_ptr, int32_t analysis_length);
@@ -0,0 +1,178 @@
+use crate::yarrow;
+use crate::privacy;
+use crate::sensitivity;
+use crate::snapping;
+
+use std::collections::HashMap;
+use probability::distribution::{Gaussian as Normal, Inverse};
+
+// half-width of the (1 - alpha) interval of the noise, which is symmetric about zero
+// https://arxiv.org/pdf/1609.04340.pdf, section 2
+pub fn get_accuracy(noise: &privacy::Noise, sensitivity: f64, alpha: f64) -> Option<f64> {
+    match noise {
+        // P(|Laplace(b)| > t) = exp(-t / b)
+        privacy::Noise::Laplace {scale} => Some(scale * sensitivity * (1. / alpha).ln()),
+        privacy::Noise::Gaussian {scale} => Some(scale * sensitivity * Normal::new(0., 1.).inverse(1. - alpha / 2.)),
+        // the accuracy of snapping depends on its bound, see get_node_accuracy
+        privacy::Noise::Snapping {epsilon: _} | privacy::Noise::Pure {epsilon: _} => None
+    }
+}
+
+// noise of the same mechanism, scaled relative to the sensitivity to meet the accuracy, or None if no scale exists
+pub fn get_noise(noise: &privacy::Noise, sensitivity: f64, accuracy: &yarrow::Accuracy) -> Option<privacy::Noise> {
+    if accuracy.value <= 0. || accuracy.alpha <= 0. || accuracy.alpha >= 1. || sensitivity <= 0. {
+        return None;
+    }
+    match noise {
+        privacy::Noise::Laplace {scale: _} => Some(privacy::Noise::Laplace {
+            scale: accuracy.value / (sensitivity * (1. / accuracy.alpha).ln())
+        }),
+        privacy::Noise::Gaussian {scale: _} => Some(privacy::Noise::Gaussian {
+            scale: accuracy.value / (sensitivity * Normal::new(0., 1.).inverse(1. - accuracy.alpha / 2.))
+        }),
+        privacy::Noise::Snapping {epsilon: _} | privacy::Noise::Pure {epsilon: _} => None
+    }
+}
+
+// smallest epsilon for which the noise is within the accuracy, where delta is the delta of the mechanism
+pub fn get_epsilon(noise: &privacy::Noise, delta: f64) -> Option<f64> {
+    match noise {
+        privacy::Noise::Laplace {scale} => Some(1. / scale),
+        privacy::Noise::Gaussian {scale: _} => privacy::compose_gaussian(std::slice::from_ref(noise), delta)
+            .map(|usage| usage.epsilon),
+        privacy::Noise::Snapping {epsilon: _} | privacy::Noise::Pure {epsilon: _} => None
+    }
+}
+
+// half-width of the (1 - alpha) interval of the noise added by a privatizer
+pub fn get_node_accuracy(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release, alpha: f64) -> Option<f64> {
+    let noise = privacy::get_noise(analysis.graph.get(&node_id)?)?;
+    let sensitivity = sensitivity::get_sensitivity(node_id, analysis, release)?;
+    match noise {
+        privacy::Noise::Snapping {epsilon} => Some(snapping::get_accuracy(
+            alpha, epsilon, sensitivity, snapping::get_bound(node_id, analysis, release)? / sensitivity, snapping::PRECISION)),
+        _ => get_accuracy(&noise, sensitivity, alpha)
+    }
+}
+
+// smallest epsilon for which the noise added by a privatizer is within the accuracy
+pub fn get_node_epsilon(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release, accuracy: &yarrow::Accuracy) -> Option<f64> {
+    let component = analysis.graph.get(&node_id)?;
+    let noise = privacy::get_noise(component)?;
+    let sensitivity = sensitivity::get_sensitivity(node_id, analysis, release)?;
+    match noise {
+        privacy::Noise::Snapping {epsilon: _} => {
+            if accuracy.value <= 0. || accuracy.alpha <= 0. || accuracy.alpha >= 1. || sensitivity <= 0. {
+                return None;
+            }
+            Some(snapping::get_epsilon(accuracy.value, accuracy.alpha, sensitivity,
+                                       snapping::get_bound(node_id, analysis, release)? / sensitivity, snapping::PRECISION))
+        },
+        _ => get_epsilon(&get_noise(&noise, sensitivity, accuracy)?,
+                         privacy::get_privacy_usage_node(component).map(|usage| usage.delta).unwrap_or(0.))
+    }
+}
+
+fn with_epsilon(component: &yarrow::Component, epsilon: f64) -> yarrow::Component {
+    use yarrow::component::Value::*;
+    let mut component = component.clone();
+    match component.value.as_mut() {
+        Some(Dpmean(x)) => x.epsilon = epsilon,
+        Some(Dpvariance(x)) => x.epsilon = epsilon,
+        Some(Dpmomentraw(x)) => x.epsilon = epsilon,
+        Some(Dpcovariance(x)) => x.epsilon = epsilon,
+        Some(Exponentialmechanism(x)) => x.epsilon = epsilon,
+        _ => ()
+    };
+    component
+}
+
+// privacy usage of each privatizer at the smallest epsilon that meets its target accuracy
+// nodes whose epsilon cannot be derived, such as for an unsupported mechanism, an infeasible target or unknown bounds,
+// are left out of the values, rather than reported at their declared epsilon
+pub fn from_accuracies(analysis: &yarrow::Analysis, release: &yarrow::Release, accuracies: &yarrow::Accuracies) -> yarrow::PrivacyUsage {
+    let mut analysis = analysis.clone();
+    let mut derived = Vec::new();
+
+    for (node_id, accuracy) in &accuracies.values {
+        let epsilon = get_node_epsilon(*node_id, &analysis, release, accuracy);
+
+        if let Some(epsilon) = epsilon {
+            let component = with_epsilon(analysis.graph.get(node_id).unwrap(), epsilon);
+            analysis.graph.insert(*node_id, component);
+            derived.push(*node_id);
+        }
+    }
+
+    // nodes without a derived epsilon keep their declared epsilon, and count towards the total
+    let mut usage = privacy::compute_privacy_usage(&analysis);
+    usage.values.retain(|node_id, _| derived.contains(node_id));
+    usage
+}
+
+pub fn to_accuracy(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release, alpha: f64) -> Option<yarrow::Accuracy> {
+    Some(yarrow::Accuracy {value: get_node_accuracy(node_id, analysis, release, alpha)?, alpha})
+}
+
+pub fn compute_accuracies(analysis: &yarrow::Analysis, release: &yarrow::Release, alpha: f64) -> yarrow::Accuracies {
+    let values: HashMap<u32, yarrow::Accuracy> = analysis.graph.keys()
+        .filter_map(|node_id| to_accuracy(*node_id, analysis, release, alpha)
+            .map(|accuracy| (*node_id, accuracy)))
+        .collect();
+    yarrow::Accuracies {values}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    #[test]
+    fn laplace_interval_contains_the_noise() {
+        let noise = privacy::Noise::Laplace {scale: 2.};
+        let half_width = get_accuracy(&noise, 0.5, 0.05).unwrap();
+        assert!((half_width - 20f64.ln()).abs() < 1e-12);
+        // P(|Laplace(b)| > t) = alpha at the half-width
+        assert!(((-half_width / (2. * 0.5)).exp() - 0.05).abs() < 1e-12);
+
+        assert_eq!(get_accuracy(&privacy::Noise::Pure {epsilon: 1.}, 0.5, 0.05), None);
+    }
+
+    #[test]
+    fn accuracies_of_privatizers() {
+        let dp_mean = yarrow::DpMean {epsilon: 0.5, mechanism: yarrow::Mechanism::Laplace as i32, ..Default::default()};
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, literal(0.)), (3, literal(10.)), (4, literal(100.)),
+            (5, component(Dpmean(dp_mean), &[("data", 1), ("minimum", 2), ("maximum", 3), ("num_records", 4)]))]);
+
+        let accuracies = compute_accuracies(&analysis, &yarrow::Release::default(), 0.05);
+        assert_eq!(accuracies.values.keys().collect::<Vec<&u32>>(), vec![&5]);
+
+        let sensitivity = sensitivity::dp_mean(&yarrow::PrivacyDefinition::default(), 100., 0., 10.);
+        let accuracy = &accuracies.values[&5];
+        assert_eq!(accuracy.alpha, 0.05);
+        assert!((accuracy.value - sensitivity / 0.5 * 20f64.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn from_accuracies_omits_underived() {
+        let dp_mean = || component(Dpmean(yarrow::DpMean {epsilon: 1., mechanism: yarrow::Mechanism::Laplace as i32, ..Default::default()}),
+                                   &[("data", 1), ("num_records", 2), ("minimum", 3), ("maximum", 4)]);
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, literal(100.)), (3, literal(0.)), (4, literal(10.)),
+            (5, dp_mean()), (6, dp_mean())]);
+        let accuracies = yarrow::Accuracies {values: vec![
+            (5, yarrow::Accuracy {value: 1., alpha: 0.05}),
+            // no epsilon meets a target with alpha of zero
+            (6, yarrow::Accuracy {value: 1., alpha: 0.})
+        ].into_iter().collect()};
+
+        let usage = from_accuracies(&analysis, &yarrow::Release::default(), &accuracies);
+        // the sensitivity is 10 / 100, and P(|Laplace(b)| > 1) = exp(-1 / b)
+        assert!((usage.values[&5].epsilon - 0.1 * (1_f64 / 0.05).ln()).abs() < 1e-12);
+        assert!(!usage.values.contains_key(&6));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::yarrow;
+use crate::components;
+use crate::privacy;
+use crate::properties;
+
+use std::collections::{HashMap, HashSet};
+
+pub fn get_sinks(analysis: &yarrow::Analysis) -> HashSet<u32> {
+    let mut node_ids = HashSet::<u32>::new();
+    // start with all nodes
+    for node_id in analysis.graph.keys() {
+        node_ids.insert(*node_id);
+    }
+
+    // remove nodes that are referenced in arguments
+    for node in analysis.graph.values() {
+        for field in node.arguments.values() {
+            node_ids.remove(&field.source_node_id);
+        }
+    }
+
+    node_ids
+}
+
+// fields that a component writes into its node evaluation
+pub fn get_output_fields(value: &yarrow::component::Value) -> Vec<&'static str> {
+    match components::get_component(value).noise() {
+        // the snapping mechanism also releases the epsilon its noise was calibrated to
+        Some(privacy::Noise::Snapping {epsilon: _}) => vec!["data", "epsilon_prime"],
+        _ => vec!["data"]
+    }
+}
+
+// first element of an array, as a float
+pub fn get_f64(value: &yarrow::ArrayNd) -> Option<f64> {
+    use yarrow::array_nd::Data;
+    match value.data.as_ref()? {
+        Data::Bool(x) => x.data.first().map(|v| if *v {1.} else {0.}),
+        Data::I64(x) => x.data.first().map(|v| *v as f64),
+        Data::F64(x) => x.data.first().cloned(),
+        _ => None
+    }
+}
+
+// all elements of a numeric array, as floats
+pub fn get_vec_f64(value: &yarrow::ArrayNd) -> Option<Vec<f64>> {
+    use yarrow::array_nd::Data;
+    match value.data.as_ref()? {
+        Data::Bool(x) => Some(x.data.iter().map(|v| if *v {1.} else {0.}).collect()),
+        Data::I64(x) => Some(x.data.iter().map(|v| *v as f64).collect()),
+        Data::F64(x) => Some(x.data.clone()),
+        _ => None
+    }
+}
+
+// number of elements in an array
+pub fn get_len(value: &yarrow::ArrayNd) -> u64 {
+    use yarrow::array_nd::Data;
+    match value.data.as_ref() {
+        Some(Data::Bytes(x)) => x.len() as u64,
+        Some(Data::Bool(x)) => x.data.len() as u64,
+        Some(Data::I64(x)) => x.data.len() as u64,
+        Some(Data::F64(x)) => x.data.len() as u64,
+        Some(Data::String(x)) => x.data.len() as u64,
+        None => 0
+    }
+}
+
+// public value of an argument, either already released or written into the analysis as a literal
+pub fn get_argument_value<'a>(node_id: u32, argument: &str,
+                              analysis: &'a yarrow::Analysis, release: &'a yarrow::Release) -> Option<&'a yarrow::ArrayNd> {
+    let field = analysis.graph.get(&node_id)?.arguments.get(argument)?;
+
+    if let Some(value) = release.values.get(&field.source_node_id)
+        .and_then(|node_release| node_release.values.get(&field.source_field)) {
+        return Some(value);
+    }
+    match analysis.graph.get(&field.source_node_id)?.value.as_ref()? {
+        yarrow::component::Value::Literal(x) => x.value.as_ref(),
+        _ => None
+    }
+}
+
+pub fn get_argument_f64(node_id: u32, argument: &str,
+                        analysis: &yarrow::Analysis, release: &yarrow::Release) -> Option<f64> {
+    get_f64(get_argument_value(node_id, argument, analysis, release)?)
+}
+
+pub fn error(node_id: u32, argument: &str, code: &str, message: String) -> yarrow::Diagnostic {
+    yarrow::Diagnostic {
+        severity: yarrow::diagnostic::Severity::Error as i32,
+        node_id,
+        argument: argument.to_owned(),
+        code: code.to_owned(),
+        message
+    }
+}
+
+pub fn warning(node_id: u32, argument: &str, code: &str, message: String) -> yarrow::Diagnostic {
+    yarrow::Diagnostic {
+        severity: yarrow::diagnostic::Severity::Warning as i32,
+        node_id,
+        argument: argument.to_owned(),
+        code: code.to_owned(),
+        message
+    }
+}
+
+pub fn is_error(diagnostic: &yarrow::Diagnostic) -> bool {
+    diagnostic.severity == yarrow::diagnostic::Severity::Error as i32
+}
+
+// order nodes such that every node comes after all of its arguments
+pub fn get_traversal(analysis: &yarrow::Analysis) -> Result<Vec<u32>, yarrow::Diagnostic> {
+
+    fn visit(node_id: u32,
+             graph: &HashMap<u32, yarrow::Component>,
+             visiting: &mut HashSet<u32>,
+             visited: &mut HashSet<u32>,
+             traversal: &mut Vec<u32>) -> Result<(), yarrow::Diagnostic> {
+
+        if visited.contains(&node_id) {
+            return Ok(());
+        }
+        if !visiting.insert(node_id) {
+            return Err(error(node_id, "", "CYCLE",
+                             format!("node {} is part of a cycle", node_id)));
+        }
+
+        let component = graph.get(&node_id)
+            .ok_or_else(|| error(node_id, "", "MISSING_NODE",
+                                 format!("node {} does not exist", node_id)))?;
+
+        // visit arguments in a stable order so that errors are reproducible
+        let mut argument_node_ids: Vec<u32> = component.arguments.values()
+            .map(|field| field.source_node_id).collect();
+        argument_node_ids.sort();
+
+        for argument_node_id in argument_node_ids {
+            visit(argument_node_id, graph, visiting, visited, traversal)?;
+        }
+
+        visiting.remove(&node_id);
+        visited.insert(node_id);
+        traversal.push(node_id);
+        Ok(())
+    }
+
+    let mut node_ids: Vec<u32> = analysis.graph.keys().cloned().collect();
+    node_ids.sort();
+
+    let mut visiting = HashSet::<u32>::new();
+    let mut visited = HashSet::<u32>::new();
+    let mut traversal = Vec::<u32>::new();
+
+    for node_id in node_ids {
+        visit(node_id, &analysis.graph, &mut visiting, &mut visited, &mut traversal)?;
+    }
+    Ok(traversal)
+}
+
+pub fn validate_arguments(node_id: u32, component: &yarrow::Component, graph: &HashMap<u32, yarrow::Component>) -> Vec<yarrow::Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    match component.value.as_ref() {
+        Some(value) => for argument_name in components::get_component(value).required_arguments() {
+            if !component.arguments.contains_key(argument_name) {
+                diagnostics.push(error(node_id, argument_name, "MISSING_ARGUMENT",
+                                       format!("node {}: missing required argument \"{}\"", node_id, argument_name)));
+            }
+        },
+        None => diagnostics.push(error(node_id, "", "COMPONENT_UNSET",
+                                       format!("node {}: component type is not set", node_id)))
+    };
+
+    let mut argument_names: Vec<&String> = component.arguments.keys().collect();
+    argument_names.sort();
+
+    for argument_name in argument_names {
+        let field = component.arguments.get(argument_name).unwrap();
+        match graph.get(&field.source_node_id) {
+            // an unset source is reported when the source node itself is checked
+            Some(source) => if let Some(source_value) = source.value.as_ref() {
+                if !get_output_fields(source_value).contains(&field.source_field.as_str()) {
+                    diagnostics.push(error(node_id, argument_name, "MISSING_FIELD",
+                                           format!("node {}: argument \"{}\" references missing field \"{}\" on node {}",
+                                                   node_id, argument_name, field.source_field, field.source_node_id)));
+                }
+            },
+            None => diagnostics.push(error(node_id, argument_name, "MISSING_NODE",
+                                           format!("node {}: argument \"{}\" references missing node {}",
+                                                   node_id, argument_name, field.source_node_id)))
+        }
+    }
+    diagnostics
+}
+
+pub fn validate_analysis(analysis: &yarrow::Analysis) -> Vec<yarrow::Diagnostic> {
+    let mut node_ids: Vec<&u32> = analysis.graph.keys().collect();
+    node_ids.sort();
+
+    let mut diagnostics: Vec<yarrow::Diagnostic> = node_ids.into_iter()
+        .flat_map(|node_id| validate_arguments(*node_id, analysis.graph.get(node_id).unwrap(), &analysis.graph))
+        .collect();
+
+    // a traversal is only meaningful once every argument resolves
+    if diagnostics.iter().any(is_error) {
+        return diagnostics;
+    }
+
+    if let Err(diagnostic) = get_traversal(analysis) {
+        diagnostics.push(diagnostic);
+        return diagnostics;
+    }
+
+    diagnostics.extend(properties::validate_properties(analysis));
+    diagnostics.extend(privacy::validate_releases(analysis));
+    diagnostics.extend(privacy::validate_parameters(analysis));
+    diagnostics.extend(privacy::validate_mechanisms(analysis));
+    diagnostics.extend(privacy::validate_privacy_usage(analysis));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    fn mean(data: u32) -> yarrow::Component {
+        component(Mean(yarrow::Mean {}), &[("data", data)])
+    }
+
+    #[test]
+    fn traversal_orders_arguments_first() {
+        let analysis = analysis_from(vec![(3, mean(2)), (2, mean(1)), (1, literal(1.))]);
+        assert_eq!(get_traversal(&analysis), Ok(vec![1, 2, 3]));
+        assert!(!validate_analysis(&analysis).iter().any(is_error));
+    }
+
+    #[test]
+    fn traversal_detects_cycles() {
+        let analysis = analysis_from(vec![(1, mean(3)), (2, mean(1)), (3, mean(2))]);
+        let cycle = get_traversal(&analysis).unwrap_err();
+        assert_eq!((cycle.code.as_str(), cycle.node_id), ("CYCLE", 1));
+        assert_eq!(codes(&validate_analysis(&analysis)), vec!["CYCLE"]);
+
+        let analysis = analysis_from(vec![(1, mean(1))]);
+        assert_eq!(get_traversal(&analysis).unwrap_err().code, "CYCLE");
+    }
+
+    #[test]
+    fn missing_argument() {
+        let analysis = analysis_from(vec![(1, literal(1.)), (2, component(Mean(yarrow::Mean {}), &[]))]);
+        let diagnostics = validate_analysis(&analysis);
+        assert_eq!(codes(&diagnostics), vec!["MISSING_ARGUMENT"]);
+        assert_eq!((diagnostics[0].node_id, diagnostics[0].argument.as_str()), (2, "data"));
+        assert!(is_error(&diagnostics[0]));
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let mut mismatched = mean(1);
+        mismatched.arguments.get_mut("data").unwrap().source_field = "edges".to_string();
+        let analysis = analysis_from(vec![
+            (1, literal(1.)), (2, mismatched), (3, mean(4)), (5, yarrow::Component::default())]);
+
+        let diagnostics = validate_analysis(&analysis);
+        assert_eq!(codes(&diagnostics), vec!["MISSING_FIELD", "MISSING_NODE", "COMPONENT_UNSET"]);
+        assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.node_id).collect::<Vec<u32>>(), vec![2, 3, 5]);
+        // the traversal is not attempted while arguments are unresolved
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.code != "CYCLE"));
+    }
+}
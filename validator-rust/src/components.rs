@@ -0,0 +1,197 @@
+use crate::yarrow;
+use crate::privacy;
+
+// what the validator and runtime need to know about each type of component
+pub trait Component {
+    // name of the component type, as it appears in analysis.proto
+    fn name(&self) -> &'static str;
+
+    // arguments that must be present for the runtime to evaluate the component
+    fn required_arguments(&self) -> Vec<&'static str>;
+
+    // arguments that calibrate the component, which must not depend on the private data
+    fn parameter_arguments(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    // noise added to the output, or None if the component does not privatize
+    fn noise(&self) -> Option<privacy::Noise> {
+        None
+    }
+
+    // privacy cost of releasing the output, or None if the component does not privatize
+    fn privacy_usage(&self) -> Option<yarrow::PrivacyUsageNode> {
+        None
+    }
+
+    fn is_privatizer(&self) -> bool {
+        self.privacy_usage().is_some()
+    }
+}
+
+macro_rules! transformation {
+    ($component:ty, $name:expr, [$($argument:expr),*]) => {
+        impl Component for $component {
+            fn name(&self) -> &'static str {
+                $name
+            }
+            fn required_arguments(&self) -> Vec<&'static str> {
+                vec![$($argument),*]
+            }
+        }
+    }
+}
+
+// privatizers carry an epsilon, delta and mechanism, and are calibrated by their public parameters
+macro_rules! privatizer {
+    ($component:ty, $name:expr, [$($argument:expr),*], [$($parameter:expr),*]) => {
+        impl Component for $component {
+            fn name(&self) -> &'static str {
+                $name
+            }
+            fn required_arguments(&self) -> Vec<&'static str> {
+                vec![$($argument,)* $($parameter),*]
+            }
+            fn parameter_arguments(&self) -> Vec<&'static str> {
+                vec![$($parameter),*]
+            }
+            fn noise(&self) -> Option<privacy::Noise> {
+                Some(privacy::get_mechanism_noise(self.epsilon, self.delta, self.mechanism))
+            }
+            fn privacy_usage(&self) -> Option<yarrow::PrivacyUsageNode> {
+                Some(privacy::get_mechanism_usage(self.epsilon, self.delta, &self.noise()?))
+            }
+        }
+    }
+}
+
+transformation!(yarrow::Literal, "Literal", []);
+transformation!(yarrow::Constant, "Constant", []);
+transformation!(yarrow::DataSource, "DataSource", ["datatype"]);
+transformation!(yarrow::Add, "Add", ["left", "right"]);
+transformation!(yarrow::Subtract, "Subtract", ["left", "right"]);
+transformation!(yarrow::Divide, "Divide", ["left", "right"]);
+transformation!(yarrow::Multiply, "Multiply", ["left", "right"]);
+transformation!(yarrow::Power, "Power", ["left", "right"]);
+transformation!(yarrow::Negate, "Negate", ["data"]);
+transformation!(yarrow::Laplace, "Laplace", ["data"]);
+transformation!(yarrow::Mean, "Mean", ["data"]);
+transformation!(yarrow::Count, "Count", ["data", "group_by"]);
+transformation!(yarrow::Median, "Median", ["data"]);
+transformation!(yarrow::Sum, "Sum", ["data"]);
+
+impl Component for yarrow::Clip {
+    fn name(&self) -> &'static str {
+        "Clip"
+    }
+    fn required_arguments(&self) -> Vec<&'static str> {
+        vec!["data", "minimum", "maximum"]
+    }
+    fn parameter_arguments(&self) -> Vec<&'static str> {
+        vec!["minimum", "maximum"]
+    }
+}
+
+impl Component for yarrow::Bin {
+    fn name(&self) -> &'static str {
+        "Bin"
+    }
+    fn required_arguments(&self) -> Vec<&'static str> {
+        vec!["data", "edges", "inclusive_left"]
+    }
+    fn parameter_arguments(&self) -> Vec<&'static str> {
+        vec!["edges", "inclusive_left"]
+    }
+}
+
+privatizer!(yarrow::DpMean, "DPMean", ["data"], ["num_records", "minimum", "maximum"]);
+privatizer!(yarrow::DpVariance, "DPVariance", ["data"], ["num_records", "minimum", "maximum"]);
+privatizer!(yarrow::DpMomentRaw, "DPMomentRaw", ["data"], ["num_records", "minimum", "maximum"]);
+privatizer!(yarrow::DpCovariance, "DPCovariance", ["data_x", "data_y"],
+            ["num_records", "minimum_x", "minimum_y", "maximum_x", "maximum_y"]);
+
+// selects a candidate rather than adding noise, so is always epsilon-DP
+impl Component for yarrow::ExponentialMechanism {
+    fn name(&self) -> &'static str {
+        "ExponentialMechanism"
+    }
+    fn required_arguments(&self) -> Vec<&'static str> {
+        vec!["data", "candidates"]
+    }
+    fn parameter_arguments(&self) -> Vec<&'static str> {
+        vec!["candidates"]
+    }
+    fn noise(&self) -> Option<privacy::Noise> {
+        Some(privacy::Noise::Pure {epsilon: self.epsilon})
+    }
+    fn privacy_usage(&self) -> Option<yarrow::PrivacyUsageNode> {
+        Some(privacy::get_mechanism_usage(self.epsilon, 0., &self.noise()?))
+    }
+}
+
+// evaluated by whichever component the runtime has registered under its name, so any arguments may be given
+impl Component for yarrow::Custom {
+    fn name(&self) -> &'static str {
+        "Custom"
+    }
+    fn required_arguments(&self) -> Vec<&'static str> {
+        vec![]
+    }
+}
+
+// the single place where component types are enumerated
+pub fn get_component(value: &yarrow::component::Value) -> &dyn Component {
+    use yarrow::component::Value::*;
+    match value {
+        Literal(x) => x,
+        Constant(x) => x,
+        Datasource(x) => x,
+        Add(x) => x,
+        Subtract(x) => x,
+        Divide(x) => x,
+        Multiply(x) => x,
+        Power(x) => x,
+        Negate(x) => x,
+        Laplace(x) => x,
+        Mean(x) => x,
+        Clip(x) => x,
+        Bin(x) => x,
+        Count(x) => x,
+        Median(x) => x,
+        Sum(x) => x,
+        Dpmean(x) => x,
+        Dpvariance(x) => x,
+        Dpmomentraw(x) => x,
+        Dpcovariance(x) => x,
+        Exponentialmechanism(x) => x,
+        Custom(x) => x,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yarrow::component::Value::*;
+
+    #[test]
+    fn components_describe_themselves() {
+        let clip = get_component(&Clip(yarrow::Clip {}));
+        assert_eq!(clip.name(), "Clip");
+        assert_eq!(clip.required_arguments(), vec!["data", "minimum", "maximum"]);
+        assert_eq!(clip.parameter_arguments(), vec!["minimum", "maximum"]);
+        assert!(!clip.is_privatizer());
+        assert!(clip.noise().is_none());
+    }
+
+    #[test]
+    fn privatizers_are_calibrated_by_parameters() {
+        let value = Dpcovariance(yarrow::DpCovariance {epsilon: 0.5, ..Default::default()});
+        let dp_covariance = get_component(&value);
+        assert_eq!(dp_covariance.name(), "DPCovariance");
+        assert_eq!(dp_covariance.required_arguments(),
+                   vec!["data_x", "data_y", "num_records", "minimum_x", "minimum_y", "maximum_x", "maximum_y"]);
+        assert_eq!(dp_covariance.parameter_arguments().len(), 5);
+        assert!(dp_covariance.is_privatizer());
+        assert_eq!(dp_covariance.privacy_usage().unwrap().epsilon, 0.5);
+    }
+}
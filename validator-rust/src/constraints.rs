@@ -0,0 +1,168 @@
+use crate::yarrow;
+use crate::base;
+use crate::components;
+use crate::properties;
+
+use std::collections::HashMap;
+
+fn literal(value: yarrow::ArrayNd) -> yarrow::Component {
+    yarrow::Component {
+        arguments: HashMap::new(),
+        value: Some(yarrow::component::Value::Literal(yarrow::Literal {value: Some(value)}))
+    }
+}
+
+fn scalar_f64(value: f64) -> yarrow::ArrayNd {
+    yarrow::ArrayNd {
+        datatype: yarrow::DataType::F64 as i32,
+        data: Some(yarrow::array_nd::Data::F64(yarrow::Array1Df64 {data: vec![value]})),
+        shape: vec![],
+        order: vec![]
+    }
+}
+
+fn scalar_i64(value: i64) -> yarrow::ArrayNd {
+    yarrow::ArrayNd {
+        datatype: yarrow::DataType::I64 as i32,
+        data: Some(yarrow::array_nd::Data::I64(yarrow::Array1Di64 {data: vec![value]})),
+        shape: vec![],
+        order: vec![]
+    }
+}
+
+// value that may fill a missing public argument, derived from what is known about the data it describes
+fn get_inferred_argument(argument: &str, component: &yarrow::Component,
+                         properties: &HashMap<u32, properties::Properties>,
+                         constraints: &yarrow::Constraints) -> Option<yarrow::ArrayNd> {
+    let source = |name: &str| component.arguments.get(name).map(|field| field.source_node_id);
+    let data = |name: &str| source(name).and_then(|node_id| properties.get(&node_id));
+
+    match argument {
+        "minimum" => data("data")?.bounds.map(|bounds| scalar_f64(bounds.0)),
+        "maximum" => data("data")?.bounds.map(|bounds| scalar_f64(bounds.1)),
+        "minimum_x" => data("data_x")?.bounds.map(|bounds| scalar_f64(bounds.0)),
+        "maximum_x" => data("data_x")?.bounds.map(|bounds| scalar_f64(bounds.1)),
+        "minimum_y" => data("data_y")?.bounds.map(|bounds| scalar_f64(bounds.0)),
+        "maximum_y" => data("data_y")?.bounds.map(|bounds| scalar_f64(bounds.1)),
+        "num_records" => data("data").or_else(|| data("data_x"))?.num_records
+            .map(|num_records| scalar_i64(num_records as i64)),
+        // edges are declared directly on the data being binned
+        "edges" => constraints.values.get(&source("data")?)?.edges.clone(),
+        _ => None
+    }
+}
+
+// fill missing public arguments with literals inferred from public constraints on the data
+pub fn infer_constraints(analysis: &yarrow::Analysis, release: &yarrow::Release,
+                         constraints: &yarrow::Constraints) -> yarrow::Analysis {
+    let mut analysis = analysis.clone();
+    let traversal = match base::get_traversal(&analysis) {
+        Ok(traversal) => traversal,
+        Err(_) => return analysis
+    };
+
+    let mut properties = HashMap::<u32, properties::Properties>::new();
+    let mut next_node_id = analysis.graph.keys().max().cloned().unwrap_or(0) + 1;
+
+    for node_id in traversal {
+        let component = analysis.graph.get(&node_id).unwrap().clone();
+
+        if let Some(value) = component.value.as_ref() {
+            let mut arguments = component.arguments.clone();
+            for argument in components::get_component(value).required_arguments() {
+                if arguments.contains_key(argument) {
+                    continue;
+                }
+                if let Some(inferred) = get_inferred_argument(argument, &component, &properties, constraints) {
+                    analysis.graph.insert(next_node_id, literal(inferred));
+                    properties.insert(next_node_id, properties::infer(next_node_id, &analysis, release, &properties));
+                    arguments.insert(argument.to_owned(), yarrow::component::Field {
+                        source_node_id: next_node_id,
+                        source_field: "data".to_owned()
+                    });
+                    next_node_id += 1;
+                }
+            }
+            analysis.graph.get_mut(&node_id).unwrap().arguments = arguments;
+        }
+
+        let mut node_properties = properties::infer(node_id, &analysis, release, &properties);
+        if let Some(constraint) = constraints.values.get(&node_id) {
+            node_properties = properties::constrain(node_properties, constraint);
+        }
+        properties.insert(node_id, node_properties);
+    }
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{self, analysis_from, component, datasource, scalar};
+    use yarrow::component::Value::*;
+
+    fn inferred(analysis: &yarrow::Analysis, node_id: u32, argument: &str) -> Option<f64> {
+        let source_node_id = analysis.graph[&node_id].arguments.get(argument)?.source_node_id;
+        match analysis.graph[&source_node_id].value.as_ref()? {
+            Literal(value) => base::get_f64(value.value.as_ref()?),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn bounds_and_record_counts_are_filled() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, component(Dpmean(yarrow::DpMean::default()), &[("data", 1)]))]);
+        let constraints = yarrow::Constraints {values: vec![(1, yarrow::Constraint {
+            minimum: Some(scalar(0.)),
+            maximum: Some(scalar(10.)),
+            num_records: Some(scalar(100.)),
+            ..Default::default()
+        })].into_iter().collect()};
+
+        let analysis = infer_constraints(&analysis, &yarrow::Release::default(), &constraints);
+        assert_eq!(inferred(&analysis, 2, "minimum"), Some(0.));
+        assert_eq!(inferred(&analysis, 2, "maximum"), Some(10.));
+        assert_eq!(inferred(&analysis, 2, "num_records"), Some(100.));
+        assert_eq!(analysis.graph.len(), 5);
+    }
+
+    #[test]
+    fn declared_arguments_are_kept() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, fixtures::literal(5.)),
+            (3, component(Dpmean(yarrow::DpMean::default()), &[("data", 1), ("maximum", 2)]))]);
+        let constraints = yarrow::Constraints {values: vec![(1, yarrow::Constraint {
+            minimum: Some(scalar(0.)),
+            maximum: Some(scalar(10.)),
+            ..Default::default()
+        })].into_iter().collect()};
+
+        let analysis = infer_constraints(&analysis, &yarrow::Release::default(), &constraints);
+        assert_eq!(analysis.graph[&3].arguments["maximum"].source_node_id, 2);
+        assert_eq!(inferred(&analysis, 3, "minimum"), Some(0.));
+        // nothing is known about the record count
+        assert!(!analysis.graph[&3].arguments.contains_key("num_records"));
+    }
+
+    #[test]
+    fn edges_are_filled() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, component(Bin(yarrow::Bin::default()), &[("data", 1)]))]);
+        let edges = yarrow::ArrayNd {
+            data: Some(yarrow::array_nd::Data::F64(yarrow::Array1Df64 {data: vec![0., 5., 10.]})),
+            shape: vec![3],
+            ..scalar(0.)
+        };
+        let constraints = yarrow::Constraints {values: vec![(1, yarrow::Constraint {
+            edges: Some(edges.clone()), ..Default::default()
+        })].into_iter().collect()};
+
+        let analysis = infer_constraints(&analysis, &yarrow::Release::default(), &constraints);
+        let source_node_id = analysis.graph[&2].arguments["edges"].source_node_id;
+        assert_eq!(analysis.graph[&source_node_id].value, Some(Literal(yarrow::Literal {value: Some(edges)})));
+    }
+}
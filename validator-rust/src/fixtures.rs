@@ -0,0 +1,42 @@
+// builders for the analyses used in tests
+use crate::yarrow;
+
+pub fn field(source_node_id: u32) -> yarrow::component::Field {
+    yarrow::component::Field {source_node_id, source_field: "data".to_string()}
+}
+
+pub fn component(value: yarrow::component::Value, arguments: &[(&str, u32)]) -> yarrow::Component {
+    yarrow::Component {
+        arguments: arguments.iter()
+            .map(|(name, source_node_id)| (name.to_string(), field(*source_node_id)))
+            .collect(),
+        value: Some(value)
+    }
+}
+
+pub fn scalar(value: f64) -> yarrow::ArrayNd {
+    yarrow::ArrayNd {
+        datatype: yarrow::DataType::F64 as i32,
+        data: Some(yarrow::array_nd::Data::F64(yarrow::Array1Df64 {data: vec![value]})),
+        order: vec![],
+        shape: vec![]
+    }
+}
+
+pub fn literal(value: f64) -> yarrow::Component {
+    component(yarrow::component::Value::Literal(yarrow::Literal {value: Some(scalar(value))}), &[])
+}
+
+pub fn datasource(datatype: yarrow::DataType) -> yarrow::Component {
+    component(yarrow::component::Value::Datasource(yarrow::DataSource {
+        datatype: datatype as i32, ..Default::default()
+    }), &[])
+}
+
+pub fn analysis_from(graph: Vec<(u32, yarrow::Component)>) -> yarrow::Analysis {
+    yarrow::Analysis {graph: graph.into_iter().collect(), ..Default::default()}
+}
+
+pub fn codes(diagnostics: &[yarrow::Diagnostic]) -> Vec<&str> {
+    diagnostics.iter().map(|diagnostic| diagnostic.code.as_str()).collect()
+}
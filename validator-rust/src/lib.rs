@@ -4,6 +4,19 @@ pub mod yarrow {
     include!(concat!(env!("OUT_DIR"), "/yarrow.rs"));
 }
 
+pub mod accuracy;
+pub mod base;
+pub mod components;
+pub mod constraints;
+pub mod privacy;
+pub mod properties;
+pub mod report;
+pub mod sensitivity;
+pub mod snapping;
+
+#[cfg(test)]
+mod fixtures;
+
 use prost::Message;
 
 // useful tutorial for proto over ffi here:
@@ -47,7 +60,11 @@ pub extern "C" fn validate_analysis(
     let analysis_buffer = unsafe {ptr_to_buffer(analysis_ptr, analysis_length)};
     let analysis: yarrow::Analysis = prost::Message::decode(analysis_buffer).unwrap();
 
-    let validation_response: yarrow::Validated = yarrow::Validated {valid: true};
+    let diagnostics = base::validate_analysis(&analysis);
+    let validation_response: yarrow::Validated = yarrow::Validated {
+        valid: !diagnostics.iter().any(base::is_error),
+        diagnostics
+    };
     buffer_to_ptr(validation_response)
 }
 
@@ -61,16 +78,18 @@ pub extern "C" fn compute_privacy(
     let analysis: yarrow::Analysis = prost::Message::decode(analysis_buffer).unwrap();
 
     let release_buffer = unsafe {ptr_to_buffer(release_ptr, release_length)};
-    let release: yarrow::Release = prost::Message::decode(release_buffer).unwrap();
+    let _release: yarrow::Release = prost::Message::decode(release_buffer).unwrap();
 
-    let privacy_usage_response: yarrow::PrivacyUsage = yarrow::PrivacyUsage {};
+    // nodes that are already released have still spent their budget, so the release is not consulted
+    let privacy_usage_response: yarrow::PrivacyUsage = privacy::compute_privacy_usage(&analysis);
     buffer_to_ptr(privacy_usage_response)
 }
 
 #[no_mangle]
 pub extern "C" fn generate_report(
     analysis_ptr: *const u8, analysis_length: i32,
-    release_ptr: *const u8, release_length: i32
+    release_ptr: *const u8, release_length: i32,
+    dataset_ptr: *const u8, dataset_length: i32
 ) -> ffi_support::ByteBuffer {
     let analysis_buffer = unsafe {ptr_to_buffer(analysis_ptr, analysis_length)};
     let analysis: yarrow::Analysis = prost::Message::decode(analysis_buffer).unwrap();
@@ -78,8 +97,11 @@ pub extern "C" fn generate_report(
     let release_buffer = unsafe {ptr_to_buffer(release_ptr, release_length)};
     let release: yarrow::Release = prost::Message::decode(release_buffer).unwrap();
 
+    let dataset_buffer = unsafe {ptr_to_buffer(dataset_ptr, dataset_length)};
+    let dataset: yarrow::Dataset = prost::Message::decode(dataset_buffer).unwrap();
+
     let report_response: yarrow::Report = yarrow::Report {
-        value: "{\"key\": \"value\"}".to_owned()
+        value: report::generate_report(&analysis, &release, &dataset).to_string()
     };
     buffer_to_ptr(report_response)
 }
@@ -99,7 +121,7 @@ pub extern "C" fn infer_constraints(
     let constraints_buffer = unsafe {ptr_to_buffer(constraints_ptr, constraints_length)};
     let constraints: yarrow::Constraints = prost::Message::decode(constraints_buffer).unwrap();
 
-    let analysis_response: yarrow::Analysis = analysis;
+    let analysis_response: yarrow::Analysis = constraints::infer_constraints(&analysis, &release, &constraints);
     buffer_to_ptr(analysis_response)
 }
 
@@ -114,7 +136,7 @@ pub extern "C" fn compute_sensitivities(
     let release_buffer = unsafe { ptr_to_buffer(release_ptr, release_length) };
     let release: yarrow::Release = prost::Message::decode(release_buffer).unwrap();
 
-    let sensitivities_response: yarrow::Sensitivities = yarrow::Sensitivities {};
+    let sensitivities_response: yarrow::Sensitivities = sensitivity::compute_sensitivities(&analysis, &release);
     buffer_to_ptr(sensitivities_response)
 }
 
@@ -134,14 +156,15 @@ pub extern "C" fn from_accuracy(
     let accuracies_buffer = unsafe { ptr_to_buffer(accuracy_ptr, accuracy_length) };
     let accuracies: yarrow::Accuracies = prost::Message::decode(accuracies_buffer).unwrap();
 
-    let privacy_usage_node_response: yarrow::PrivacyUsageNode = yarrow::PrivacyUsageNode {};
-    buffer_to_ptr(privacy_usage_node_response)
+    let privacy_usage_response: yarrow::PrivacyUsage = accuracy::from_accuracies(&analysis, &release, &accuracies);
+    buffer_to_ptr(privacy_usage_response)
 }
 
 #[no_mangle]
 pub extern "C" fn to_accuracy(
     analysis_ptr: *const u8, analysis_length: i32,
-    release_ptr: *const u8, release_length: i32
+    release_ptr: *const u8, release_length: i32,
+    alpha: f64
 ) -> ffi_support::ByteBuffer {
 
     let analysis_buffer = unsafe { ptr_to_buffer(analysis_ptr, analysis_length) };
@@ -150,6 +173,6 @@ pub extern "C" fn to_accuracy(
     let release_buffer = unsafe { ptr_to_buffer(release_ptr, release_length) };
     let release: yarrow::Release = prost::Message::decode(release_buffer).unwrap();
 
-    let accuracies_response: yarrow::Accuracies = yarrow::Accuracies {};
+    let accuracies_response: yarrow::Accuracies = accuracy::compute_accuracies(&analysis, &release, alpha);
     buffer_to_ptr(accuracies_response)
 }
@@ -0,0 +1,833 @@
+use crate::yarrow;
+use crate::base;
+use crate::components;
+use crate::properties;
+
+use std::collections::HashMap;
+use probability::distribution::{Distribution, Gaussian as Normal};
+
+pub fn is_privatizer(component: &yarrow::Component) -> bool {
+    get_privacy_usage_node(component).is_some()
+}
+
+// privacy usage declared by a single component, or None if it does not privatize
+pub fn get_privacy_usage_node(component: &yarrow::Component) -> Option<yarrow::PrivacyUsageNode> {
+    components::get_component(component.value.as_ref()?).privacy_usage()
+}
+
+// number of individuals protected as a unit, where an undeclared group size protects individuals
+// the runtime scales sensitivities by the group size, so accounted usage is with respect to groups
+pub fn get_group_size(definition: &yarrow::PrivacyDefinition) -> u32 {
+    definition.group_size.max(1)
+}
+
+// noise added by a privatizer, with scales relative to the sensitivity of the statistic
+#[derive(Clone, Debug)]
+pub enum Noise {
+    Laplace {scale: f64},
+    Gaussian {scale: f64},
+    // Laplace noise rounded and clamped to a bound, whose scale depends on the bound
+    Snapping {epsilon: f64},
+    // an epsilon-DP mechanism whose noise distribution is not known
+    Pure {epsilon: f64}
+}
+
+pub fn get_noise(component: &yarrow::Component) -> Option<Noise> {
+    components::get_component(component.value.as_ref()?).noise()
+}
+
+// noise added by a mechanism at the given epsilon, where delta is only used by the Gaussian mechanism
+pub fn get_mechanism_noise(epsilon: f64, delta: f64, mechanism: i32) -> Noise {
+    match yarrow::Mechanism::from_i32(mechanism) {
+        Some(yarrow::Mechanism::Laplace) => Noise::Laplace {scale: 1. / epsilon},
+        Some(yarrow::Mechanism::Gaussian) => Noise::Gaussian {scale: get_gaussian_scale(epsilon, delta)},
+        Some(yarrow::Mechanism::Snapping) => Noise::Snapping {epsilon},
+        _ => Noise::Pure {epsilon}
+    }
+}
+
+// privacy usage of a mechanism, with its costs under the other distances
+pub fn get_mechanism_usage(epsilon: f64, delta: f64, noise: &Noise) -> yarrow::PrivacyUsageNode {
+    yarrow::PrivacyUsageNode {
+        epsilon,
+        delta: match noise {
+            Noise::Gaussian {scale: _} => delta,
+            _ => 0.
+        },
+        rho: get_rho(noise),
+        mu: get_mu(noise).unwrap_or(0.)
+    }
+}
+
+// smallest scale, relative to the L2 sensitivity, at which Gaussian noise is (epsilon, delta)-DP
+// this is the analytic Gaussian mechanism, which also holds for epsilon > 1
+// https://arxiv.org/pdf/1805.06530.pdf, theorem 8
+pub fn get_gaussian_scale(epsilon: f64, delta: f64) -> f64 {
+    if epsilon <= 0. || delta <= 0. {
+        return f64::INFINITY;
+    }
+    if delta >= 1. {
+        return 0.;
+    }
+
+    // delta increases in mu, so bisect for the largest mu within the delta bound
+    let mut lower = 0.;
+    let mut upper = 1.;
+    while gaussian_delta(upper, epsilon) <= delta {
+        upper *= 2.;
+    }
+    for _ in 0..100 {
+        let middle = (lower + upper) / 2.;
+        if gaussian_delta(middle, epsilon) <= delta {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    1. / lower
+}
+
+// zero-concentrated DP cost of the noise
+// https://arxiv.org/pdf/1605.02065.pdf, propositions 1.4 and 1.6
+pub fn get_rho(noise: &Noise) -> f64 {
+    match noise {
+        Noise::Laplace {scale} => 1. / (2. * scale.powi(2)),
+        Noise::Gaussian {scale} => 1. / (2. * scale.powi(2)),
+        Noise::Snapping {epsilon} | Noise::Pure {epsilon} => epsilon.powi(2) / 2.
+    }
+}
+
+// Gaussian DP cost of the noise, or None if the mechanism is not Gaussian
+// https://arxiv.org/pdf/1905.02383.pdf, theorem 2.7
+pub fn get_mu(noise: &Noise) -> Option<f64> {
+    match noise {
+        Noise::Gaussian {scale} => Some(1. / scale),
+        _ => None
+    }
+}
+
+// sequential composition: epsilons, deltas and rhos of all privatizers add
+pub fn compose_basic(usages: &[yarrow::PrivacyUsageNode]) -> yarrow::PrivacyUsageNode {
+    yarrow::PrivacyUsageNode {
+        epsilon: usages.iter().map(|usage| usage.epsilon).sum(),
+        delta: usages.iter().map(|usage| usage.delta).sum(),
+        rho: usages.iter().map(|usage| usage.rho).sum(),
+        mu: 0.
+    }
+}
+
+// heterogeneous advanced composition, spending an additional `delta_slack`
+pub fn compose_advanced(usages: &[yarrow::PrivacyUsageNode], delta_slack: f64) -> Option<yarrow::PrivacyUsageNode> {
+    if usages.is_empty() || delta_slack <= 0. {
+        return None;
+    }
+
+    let sum_squares: f64 = usages.iter().map(|usage| usage.epsilon.powi(2)).sum();
+    // (e^eps - 1) / (e^eps + 1) == tanh(eps / 2)
+    let expected_loss: f64 = usages.iter().map(|usage| usage.epsilon * (usage.epsilon / 2.).tanh()).sum();
+
+    let epsilon = f64::min(
+        expected_loss + (2. * sum_squares * (1. / delta_slack).ln()).sqrt(),
+        expected_loss + (2. * sum_squares * (std::f64::consts::E + sum_squares.sqrt() / delta_slack).ln()).sqrt());
+    // 1 - (1 - delta_slack) * prod(1 - delta_i), without cancellation for tiny deltas
+    let delta = -(usages.iter().map(|usage| (-usage.delta).ln_1p()).sum::<f64>() + (-delta_slack).ln_1p()).exp_m1();
+
+    Some(yarrow::PrivacyUsageNode {epsilon, delta, rho: 0., mu: 0.})
+}
+
+// optimal k-fold composition, at the smallest epsilon whose delta fits within `delta_budget`
+pub fn compose_optimal(usages: &[yarrow::PrivacyUsageNode], delta_budget: f64) -> Option<yarrow::PrivacyUsageNode> {
+    if usages.is_empty() {
+        return None;
+    }
+
+    // the theorem is for homogeneous mechanisms, and every mechanism is also (max eps, max delta)-DP
+    let epsilon = usages.iter().map(|usage| usage.epsilon).fold(0., f64::max);
+    let delta = usages.iter().map(|usage| usage.delta).fold(0., f64::max);
+    let k = usages.len();
+
+    // terms are computed in log space, as e^(k eps) overflows for long analyses
+    let ln_normalizer = k as f64 * epsilon.exp().ln_1p();
+    let mut ln_binomials = vec![0.; k + 1];
+    for l in 1..=k {
+        ln_binomials[l] = ln_binomials[l - 1] + ((k - l + 1) as f64).ln() - (l as f64).ln();
+    }
+
+    // delta_i grows with i while the epsilon shrinks, so the largest admissible i is the tightest
+    (0..=k / 2).rev().find_map(|i| {
+        let delta_i: f64 = (0..i).map(|l| {
+            (ln_binomials[l] + (k - l) as f64 * epsilon - ln_normalizer).exp()
+                - (ln_binomials[l] + (k - 2 * i + l) as f64 * epsilon - ln_normalizer).exp()
+        }).sum();
+        let total_delta = -(k as f64 * (-delta).ln_1p() + (-delta_i).ln_1p()).exp_m1();
+
+        if total_delta <= delta_budget {
+            Some(yarrow::PrivacyUsageNode {epsilon: (k - 2 * i) as f64 * epsilon, delta: total_delta, rho: 0., mu: 0.})
+        } else {
+            None
+        }
+    })
+}
+
+// orders at which Renyi divergences are tracked
+pub const RENYI_ORDERS: [f64; 23] = [
+    1.25, 1.5, 1.75, 2., 2.25, 2.5, 3., 3.5, 4., 4.5, 5., 6., 8.,
+    10., 12., 16., 20., 24., 32., 48., 64., 128., 256.];
+
+// Renyi divergence of order `alpha` between the noise distributions of neighboring datasets
+// https://arxiv.org/pdf/1702.07476.pdf, table 2
+pub fn renyi_divergence(noise: &Noise, alpha: f64) -> f64 {
+    match noise {
+        Noise::Laplace {scale} => {
+            // log-sum-exp of the two terms, as e^((alpha - 1) / scale) overflows for large orders
+            let positive = (alpha / (2. * alpha - 1.)).ln() + (alpha - 1.) / scale;
+            let negative = ((alpha - 1.) / (2. * alpha - 1.)).ln() - alpha / scale;
+            let maximum = positive.max(negative);
+            (maximum + (positive.min(negative) - maximum).exp().ln_1p()) / (alpha - 1.)
+        },
+        Noise::Gaussian {scale} => alpha / (2. * scale.powi(2)),
+        // pure DP bounds the divergence at every order, and implies (eps^2 / 2)-zCDP
+        Noise::Snapping {epsilon} | Noise::Pure {epsilon} => epsilon.min(alpha * epsilon.powi(2) / 2.)
+    }
+}
+
+// compose Renyi curves over the grid of orders, then convert to (epsilon, delta)
+// https://arxiv.org/pdf/1702.07476.pdf, proposition 3
+pub fn compose_renyi(noises: &[Noise], delta: f64) -> Option<yarrow::PrivacyUsageNode> {
+    if noises.is_empty() || delta <= 0. {
+        return None;
+    }
+
+    let epsilon = RENYI_ORDERS.iter()
+        .map(|alpha| noises.iter().map(|noise| renyi_divergence(noise, *alpha)).sum::<f64>()
+            + (1. / delta).ln() / (alpha - 1.))
+        .fold(f64::INFINITY, f64::min);
+
+    Some(yarrow::PrivacyUsageNode {epsilon, delta, rho: 0., mu: 0.})
+}
+
+// zCDP composes additively, then converts to (epsilon, delta)
+// https://arxiv.org/pdf/1605.02065.pdf, proposition 1.3
+pub fn compose_concentrated(noises: &[Noise], delta: f64) -> Option<yarrow::PrivacyUsageNode> {
+    if noises.is_empty() || delta <= 0. {
+        return None;
+    }
+
+    let rho: f64 = noises.iter().map(get_rho).sum();
+    let epsilon = rho + 2. * (rho * (1. / delta).ln()).sqrt();
+
+    Some(yarrow::PrivacyUsageNode {epsilon, delta, rho, mu: 0.})
+}
+
+// delta of a mu-GDP mechanism at the given epsilon
+// https://arxiv.org/pdf/1905.02383.pdf, corollary 2.13
+pub fn gaussian_delta(mu: f64, epsilon: f64) -> f64 {
+    let normal = Normal::new(0., 1.);
+    normal.distribution(-epsilon / mu + mu / 2.) - epsilon.exp() * normal.distribution(-epsilon / mu - mu / 2.)
+}
+
+// Gaussian DP composes as the root sum of squares, then converts to (epsilon, delta)
+// https://arxiv.org/pdf/1905.02383.pdf, corollary 3.3
+pub fn compose_gaussian(noises: &[Noise], delta: f64) -> Option<yarrow::PrivacyUsageNode> {
+    if noises.is_empty() || delta <= 0. {
+        return None;
+    }
+
+    // every mechanism must be Gaussian for the composition to be mu-GDP
+    let mus: Vec<f64> = noises.iter().map(get_mu).collect::<Option<Vec<f64>>>()?;
+    let mu = mus.iter().map(|mu| mu.powi(2)).sum::<f64>().sqrt();
+
+    // delta decreases in epsilon, so bisect for the smallest epsilon within the delta bound
+    let mut lower = 0.;
+    let mut upper = 1.;
+    while gaussian_delta(mu, upper) > delta {
+        upper *= 2.;
+    }
+    for _ in 0..100 {
+        let middle = (lower + upper) / 2.;
+        if gaussian_delta(mu, middle) > delta {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+
+    Some(yarrow::PrivacyUsageNode {epsilon: upper, delta, rho: 0., mu})
+}
+
+// compose privatizers with the accountant selected in the privacy definition, falling back to basic composition
+pub fn compose(privatizers: &[&yarrow::Component], definition: &yarrow::PrivacyDefinition) -> (yarrow::PrivacyUsageNode, yarrow::Composition) {
+    use yarrow::Composition::*;
+    use yarrow::privacy_definition::Distance;
+
+    let usages: Vec<yarrow::PrivacyUsageNode> = privatizers.iter()
+        .filter_map(|component| get_privacy_usage_node(component)).collect();
+    let noises: Vec<Noise> = privatizers.iter()
+        .filter_map(|component| get_noise(component)).collect();
+
+    let basic = compose_basic(&usages);
+    let mut candidates = vec![(basic.clone(), Basic)];
+
+    match Distance::from_i32(definition.distance) {
+        Some(Distance::Approximate) => {
+            // the declared delta bound, less the delta already spent by mechanisms, is the slack
+            let advanced = compose_advanced(&usages, definition.delta - basic.delta);
+
+            match yarrow::Composition::from_i32(definition.composition) {
+                Some(Advanced) => candidates.extend(advanced.map(|usage| (usage, Advanced))),
+                Some(Optimal) => {
+                    candidates.extend(advanced.map(|usage| (usage, Advanced)));
+                    candidates.extend(compose_optimal(&usages, definition.delta).map(|usage| (usage, Optimal)));
+                },
+                _ => ()
+            }
+        },
+        Some(Distance::Renyi) =>
+            candidates.extend(compose_renyi(&noises, definition.delta).map(|usage| (usage, Renyi))),
+        Some(Distance::Concentrated) =>
+            candidates.extend(compose_concentrated(&noises, definition.delta).map(|usage| (usage, Concentrated))),
+        Some(Distance::F) =>
+            candidates.extend(compose_gaussian(&noises, definition.delta).map(|usage| (usage, GaussianDp))),
+        _ => ()
+    }
+
+    // every candidate is a valid bound, so report the tightest
+    let (mut total, composition) = candidates.into_iter()
+        .fold(None, |best: Option<(yarrow::PrivacyUsageNode, yarrow::Composition)>, candidate| match best {
+            Some(best) if best.0.epsilon <= candidate.0.epsilon => Some(best),
+            _ => Some(candidate)
+        }).unwrap();
+
+    // rho is additive regardless of which accountant bounds epsilon
+    total.rho = basic.rho;
+    (total, composition)
+}
+
+pub fn compute_privacy_usage(analysis: &yarrow::Analysis) -> yarrow::PrivacyUsage {
+    let values: HashMap<u32, yarrow::PrivacyUsageNode> = analysis.graph.iter()
+        .filter_map(|(node_id, component)| get_privacy_usage_node(component)
+            .map(|usage| (*node_id, usage)))
+        .collect();
+
+    let mut node_ids: Vec<&u32> = values.keys().collect();
+    node_ids.sort();
+    let privatizers: Vec<&yarrow::Component> = node_ids.into_iter()
+        .map(|node_id| analysis.graph.get(node_id).unwrap()).collect();
+
+    let (total, composition) = compose(&privatizers, &analysis.privacy_definition.clone().unwrap_or_default());
+    yarrow::PrivacyUsage {
+        total: Some(total),
+        composition: composition as i32,
+        values
+    }
+}
+
+// floating-point slack when comparing summed usage against the declared bound
+const BUDGET_TOLERANCE: f64 = 1e-12;
+
+// check the composed privacy usage against the upper bounds in the privacy definition
+pub fn validate_privacy_usage(analysis: &yarrow::Analysis) -> Vec<yarrow::Diagnostic> {
+    let total = compute_privacy_usage(analysis).total.unwrap_or_default();
+
+    let definition = analysis.privacy_definition.clone().unwrap_or_default();
+
+    let mut diagnostics = Vec::new();
+    // proto3 cannot distinguish an unset bound from zero, so a zero epsilon or rho means undeclared
+    // delta has no such exemption: an undeclared delta is a bound of zero
+    if definition.epsilon <= 0. && definition.rho <= 0. {
+        diagnostics.push(base::warning(0, "", "NO_PRIVACY_BUDGET", format!(
+            "no epsilon or rho upper bound is declared in the privacy definition; the analysis uses epsilon {}", total.epsilon)));
+    }
+    if definition.composition != yarrow::Composition::Basic as i32
+        && definition.distance != yarrow::privacy_definition::Distance::Approximate as i32 {
+        diagnostics.push(base::warning(0, "", "COMPOSITION_UNSUPPORTED", format!(
+            "{:?} composition requires an APPROXIMATE distance, so basic composition is used instead",
+            yarrow::Composition::from_i32(definition.composition).unwrap_or(yarrow::Composition::Basic))));
+    }
+    if definition.distance == yarrow::privacy_definition::Distance::Renyi as i32 && definition.delta <= 0. {
+        diagnostics.push(base::warning(0, "delta", "RENYI_REQUIRES_DELTA", "Renyi accounting converts to (epsilon, delta) with the declared delta, \
+            which is zero, so basic composition is used instead".to_owned()));
+    }
+    if definition.distance == yarrow::privacy_definition::Distance::F as i32 {
+        let mut node_ids: Vec<&u32> = analysis.graph.keys().collect();
+        node_ids.sort();
+        for node_id in node_ids {
+            if let Some(noise) = get_noise(analysis.graph.get(node_id).unwrap()) {
+                if get_mu(&noise).is_none() {
+                    diagnostics.push(base::warning(*node_id, "", "GAUSSIAN_DP_UNSUPPORTED", format!(
+                        "node {}: Gaussian DP accounting only applies to Gaussian mechanisms, so basic composition is used instead", node_id)));
+                }
+            }
+        }
+    }
+    if definition.rho > 0. && total.rho > definition.rho * (1. + BUDGET_TOLERANCE) {
+        diagnostics.push(base::error(0, "", "BUDGET_EXCEEDED", format!(
+            "the analysis uses rho {}, which exceeds the declared upper bound of {}",
+            total.rho, definition.rho)));
+    }
+    if definition.epsilon > 0. && total.epsilon > definition.epsilon * (1. + BUDGET_TOLERANCE) {
+        diagnostics.push(base::error(0, "", "BUDGET_EXCEEDED", format!(
+            "the analysis uses epsilon {}, which exceeds the declared upper bound of {}",
+            total.epsilon, definition.epsilon)));
+    }
+    if total.delta > definition.delta * (1. + BUDGET_TOLERANCE) {
+        diagnostics.push(base::error(0, "", "BUDGET_EXCEEDED", format!(
+            "the analysis uses delta {}, which exceeds the declared upper bound of {}",
+            total.delta, definition.delta)));
+    }
+    diagnostics
+}
+
+// distances under which the Gaussian mechanism can be accounted for
+pub const GAUSSIAN_DISTANCES: [yarrow::privacy_definition::Distance; 4] = [
+    yarrow::privacy_definition::Distance::Approximate,
+    yarrow::privacy_definition::Distance::Renyi,
+    yarrow::privacy_definition::Distance::Concentrated,
+    yarrow::privacy_definition::Distance::F];
+
+// check that each mechanism can be accounted for under the distance in the privacy definition
+pub fn validate_mechanisms(analysis: &yarrow::Analysis) -> Vec<yarrow::Diagnostic> {
+    use yarrow::privacy_definition::Distance;
+    let distance = analysis.privacy_definition.as_ref()
+        .and_then(|definition| Distance::from_i32(definition.distance))
+        .unwrap_or(Distance::Pure);
+
+    let mut node_ids: Vec<&u32> = analysis.graph.keys().collect();
+    node_ids.sort();
+
+    let mut diagnostics = Vec::new();
+    for node_id in node_ids {
+        let component = analysis.graph.get(node_id).unwrap();
+        let usage = match get_privacy_usage_node(component) {
+            Some(usage) => usage,
+            None => continue
+        };
+        // noise and selection probabilities are undefined unless epsilon is positive
+        if !(usage.epsilon > 0. && usage.epsilon.is_finite()) {
+            diagnostics.push(base::error(*node_id, "epsilon", "INVALID_EPSILON", format!(
+                "node {}: epsilon must be positive and finite, but {} was given", node_id, usage.epsilon)));
+        }
+        if let Some(Noise::Gaussian {scale: _}) = get_noise(component) {
+            if !GAUSSIAN_DISTANCES.contains(&distance) {
+                diagnostics.push(base::error(*node_id, "", "MECHANISM_UNSUPPORTED", format!(
+                    "node {}: the Gaussian mechanism does not satisfy {} differential privacy, \
+                    so the distance must be one of {}", node_id, format!("{:?}", distance).to_uppercase(),
+                    GAUSSIAN_DISTANCES.iter().map(|distance| format!("{:?}", distance).to_uppercase())
+                        .collect::<Vec<String>>().join(", "))));
+            }
+            if usage.delta <= 0. || usage.delta >= 1. {
+                diagnostics.push(base::error(*node_id, "delta", "INVALID_DELTA", format!(
+                    "node {}: the Gaussian mechanism requires a delta between 0 and 1, but {} was given",
+                    node_id, usage.delta)));
+            }
+        }
+    }
+    diagnostics
+}
+
+// path from a data source to the node, through nodes that carry private data without privatization
+pub fn get_leak_path(node_id: u32, analysis: &yarrow::Analysis,
+                     properties: &HashMap<u32, properties::Properties>) -> Vec<u32> {
+    let is_private = |node_id: &u32| properties.get(node_id)
+        .map(|node_properties| node_properties.private).unwrap_or(false);
+
+    let mut path = vec![node_id];
+    while let Some(component) = analysis.graph.get(path.last().unwrap()) {
+        // follow arguments in a stable order, so that the reported path is reproducible
+        let mut source_node_ids: Vec<u32> = component.arguments.values()
+            .map(|field| field.source_node_id)
+            .filter(|source_node_id| is_private(source_node_id) && !path.contains(source_node_id))
+            .collect();
+        source_node_ids.sort();
+        match source_node_ids.first() {
+            Some(source_node_id) => path.push(*source_node_id),
+            None => break
+        }
+    }
+    path.reverse();
+    path
+}
+
+fn describe_path(path: &[u32], analysis: &yarrow::Analysis) -> String {
+    path.iter()
+        .map(|node_id| format!("{} ({})", analysis.graph.get(node_id)
+            .and_then(|component| component.value.as_ref())
+            .map(|value| components::get_component(value).name()).unwrap_or("unknown"), node_id))
+        .collect::<Vec<String>>().join(" -> ")
+}
+
+// every sink is released, so each must depend on the private data only through a privatizer
+pub fn validate_releases(analysis: &yarrow::Analysis) -> Vec<yarrow::Diagnostic> {
+    let properties = properties::infer_properties(analysis, &yarrow::Release::default());
+    let mut sink_node_ids: Vec<u32> = base::get_sinks(analysis).into_iter().collect();
+    sink_node_ids.sort();
+
+    sink_node_ids.into_iter()
+        .filter(|node_id| properties.get(node_id).map(|node_properties| node_properties.private).unwrap_or(false))
+        .map(|node_id| base::error(node_id, "", "PRIVACY_LEAK",
+                                   format!("node {}: private data is released without privatization, via {}",
+                                           node_id, describe_path(&get_leak_path(node_id, analysis, &properties), analysis))))
+        .collect()
+}
+
+// noise is calibrated to parameters, so parameters computed from the private data would leak it through the calibration
+pub fn validate_parameters(analysis: &yarrow::Analysis) -> Vec<yarrow::Diagnostic> {
+    let properties = properties::infer_properties(analysis, &yarrow::Release::default());
+    let mut node_ids: Vec<&u32> = analysis.graph.keys().collect();
+    node_ids.sort();
+
+    let mut diagnostics = Vec::new();
+    for node_id in node_ids {
+        let component = analysis.graph.get(node_id).unwrap();
+        let value = match component.value.as_ref() {
+            Some(value) => value,
+            None => continue
+        };
+        for argument in components::get_component(value).parameter_arguments() {
+            let source_node_id = match component.arguments.get(argument) {
+                Some(field) => field.source_node_id,
+                None => continue
+            };
+            if properties.get(&source_node_id).map(|source| source.private).unwrap_or(false) {
+                diagnostics.push(base::error(*node_id, argument, "PRIVATE_PARAMETER",
+                                             format!("node {}: argument \"{}\" depends on private data without privatization, via {}",
+                                                     node_id, argument, describe_path(&get_leak_path(source_node_id, analysis, &properties), analysis))));
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    fn dp_mean(epsilon: f64) -> yarrow::Component {
+        component(Dpmean(yarrow::DpMean {epsilon, ..Default::default()}), &[("data", 1)])
+    }
+
+    #[test]
+    fn privatizers_declare_usage() {
+        let usage = get_privacy_usage_node(&dp_mean(0.5)).unwrap();
+        assert_eq!((usage.epsilon, usage.delta), (0.5, 0.));
+        assert!(is_privatizer(&dp_mean(0.5)));
+        assert!(!is_privatizer(&literal(1.)));
+        assert!(!is_privatizer(&yarrow::Component::default()));
+    }
+
+    #[test]
+    fn usages_compose_by_summation() {
+        let analysis = analysis_from(vec![(1, literal(1.)), (2, dp_mean(0.5)), (3, dp_mean(0.25))]);
+        let usage = compute_privacy_usage(&analysis);
+
+        let total = usage.total.as_ref().unwrap();
+        assert_eq!((total.epsilon, total.delta), (0.75, 0.));
+        let mut privatized: Vec<u32> = usage.values.keys().copied().collect();
+        privatized.sort();
+        assert_eq!(privatized, vec![2, 3]);
+        assert_eq!(usage.values[&3].epsilon, 0.25);
+    }
+
+    fn budgeted(analysis: yarrow::Analysis, epsilon: f64, delta: f64) -> yarrow::Analysis {
+        yarrow::Analysis {privacy_definition: Some(yarrow::PrivacyDefinition {epsilon, delta, ..Default::default()}), ..analysis}
+    }
+
+    #[test]
+    fn usage_within_budget() {
+        let analysis = budgeted(analysis_from(vec![(2, dp_mean(0.5)), (3, dp_mean(0.5))]), 1., 0.);
+        assert!(validate_privacy_usage(&analysis).is_empty());
+    }
+
+    #[test]
+    fn usage_exceeds_budget() {
+        let analysis = budgeted(analysis_from(vec![(2, dp_mean(0.5)), (3, dp_mean(0.75))]), 1., 0.);
+        let diagnostics = validate_privacy_usage(&analysis);
+        assert_eq!(codes(&diagnostics), vec!["BUDGET_EXCEEDED"]);
+        assert!(base::is_error(&diagnostics[0]));
+    }
+
+    #[test]
+    fn undeclared_budget_warns() {
+        let diagnostics = validate_privacy_usage(&analysis_from(vec![(2, dp_mean(0.5))]));
+        assert_eq!(codes(&diagnostics), vec!["NO_PRIVACY_BUDGET"]);
+        assert!(!base::is_error(&diagnostics[0]));
+    }
+
+    #[test]
+    fn delta_is_bounded_without_epsilon() {
+        let mean = component(Dpmean(yarrow::DpMean {epsilon: 0.5, delta: 1e-6, mechanism: yarrow::Mechanism::Gaussian as i32}), &[]);
+        let diagnostics = validate_privacy_usage(&budgeted(analysis_from(vec![(2, mean)]), 0., 1e-7));
+        assert_eq!(codes(&diagnostics), vec!["NO_PRIVACY_BUDGET", "BUDGET_EXCEEDED"]);
+        assert!(diagnostics[1].message.contains("delta"));
+    }
+
+    #[test]
+    fn undeclared_group_size_protects_individuals() {
+        let definition = |group_size| yarrow::PrivacyDefinition {group_size, ..Default::default()};
+        assert_eq!(get_group_size(&definition(0)), 1);
+        assert_eq!(get_group_size(&definition(1)), 1);
+        assert_eq!(get_group_size(&definition(4)), 4);
+    }
+
+    #[test]
+    fn unprivatized_release_is_a_leak() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, component(Mean(yarrow::Mean {}), &[("data", 1)]))]);
+
+        let diagnostics = validate_releases(&analysis);
+        assert_eq!(codes(&diagnostics), vec!["PRIVACY_LEAK"]);
+        assert_eq!(diagnostics[0].node_id, 2);
+        assert_eq!(diagnostics[0].message,
+                   "node 2: private data is released without privatization, via DataSource (1) -> Mean (2)");
+    }
+
+    #[test]
+    fn privatized_release_is_not_a_leak() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, literal(0.)), (3, literal(10.)), (4, literal(100.)),
+            (5, component(Dpmean(yarrow::DpMean {epsilon: 0.5, ..Default::default()}),
+                          &[("data", 1), ("minimum", 2), ("maximum", 3), ("num_records", 4)]))]);
+        assert!(validate_releases(&analysis).is_empty());
+    }
+
+    #[test]
+    fn private_parameter_is_rejected() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, component(Mean(yarrow::Mean {}), &[("data", 1)])),
+            (3, literal(0.)), (4, literal(100.)),
+            (5, component(Dpmean(yarrow::DpMean {epsilon: 0.5, ..Default::default()}),
+                          &[("data", 1), ("minimum", 3), ("maximum", 2), ("num_records", 4)]))]);
+
+        let diagnostics = validate_parameters(&analysis);
+        assert_eq!(codes(&diagnostics), vec!["PRIVATE_PARAMETER"]);
+        assert_eq!((diagnostics[0].node_id, diagnostics[0].argument.as_str()), (5, "maximum"));
+        assert_eq!(diagnostics[0].message,
+                   "node 5: argument \"maximum\" depends on private data without privatization, via DataSource (1) -> Mean (2)");
+    }
+
+    fn usages(k: usize, epsilon: f64, delta: f64) -> Vec<yarrow::PrivacyUsageNode> {
+        vec![yarrow::PrivacyUsageNode {epsilon, delta, rho: 0., mu: 0.}; k]
+    }
+
+    // https://arxiv.org/pdf/1311.0776.pdf, theorem 3.5, for k (epsilon, 0)-DP mechanisms
+    fn kairouz(k: f64, epsilon: f64, delta_slack: f64) -> f64 {
+        let expected_loss = k * epsilon * (epsilon.exp() - 1.) / (epsilon.exp() + 1.);
+        let sum_squares = k * epsilon.powi(2);
+        f64::min(
+            expected_loss + (2. * sum_squares * (1. / delta_slack).ln()).sqrt(),
+            expected_loss + (2. * sum_squares * (std::f64::consts::E + sum_squares.sqrt() / delta_slack).ln()).sqrt())
+    }
+
+    #[test]
+    fn compose_advanced_matches_kairouz() {
+        let usage = compose_advanced(&usages(10, 0.1, 0.), 1e-5).unwrap();
+        assert!((usage.epsilon - kairouz(10., 0.1, 1e-5)).abs() < 1e-12);
+        assert!((usage.epsilon - 1.48952).abs() < 1e-4);
+        assert!((usage.delta - 1e-5).abs() < 1e-15);
+
+        // the bound wins over basic composition for long analyses
+        let usage = compose_advanced(&usages(1000, 0.01, 0.), 1e-5).unwrap();
+        assert!((usage.epsilon - kairouz(1000., 0.01, 1e-5)).abs() < 1e-12);
+        assert!(usage.epsilon < 1000. * 0.01);
+    }
+
+    #[test]
+    fn compose_advanced_edge_cases() {
+        // no slack, or no mechanisms, has no advanced bound
+        assert!(compose_advanced(&usages(10, 0.1, 0.), 0.).is_none());
+        assert!(compose_advanced(&[], 1e-5).is_none());
+
+        let usage = compose_advanced(&usages(1, 0.1, 0.), 1e-5).unwrap();
+        assert!((usage.epsilon - kairouz(1., 0.1, 1e-5)).abs() < 1e-12);
+
+        // the deltas of the mechanisms are spent along with the slack
+        let usage = compose_advanced(&usages(10, 0.1, 1e-6), 1e-5).unwrap();
+        assert!((usage.delta - (1. - (1. - 1e-5) * (1. - 1e-6_f64).powi(10))).abs() < 1e-15);
+    }
+
+    #[test]
+    fn compose_optimal_within_advanced() {
+        for (k, epsilon) in &[(10, 0.1), (100, 0.1), (1000, 0.01), (50, 1.)] {
+            let optimal = compose_optimal(&usages(*k, *epsilon, 0.), 1e-5).unwrap();
+            let advanced = compose_advanced(&usages(*k, *epsilon, 0.), 1e-5).unwrap();
+            assert!(optimal.epsilon <= advanced.epsilon + 1e-12);
+            assert!(optimal.epsilon <= *k as f64 * epsilon + 1e-12);
+            assert!(optimal.delta <= 1e-5);
+        }
+    }
+
+    #[test]
+    fn compose_optimal_edge_cases() {
+        assert!(compose_optimal(&[], 1e-5).is_none());
+
+        // a single mechanism is its own optimal composition
+        let usage = compose_optimal(&usages(1, 0.5, 0.), 1e-5).unwrap();
+        assert!((usage.epsilon - 0.5).abs() < 1e-12);
+        assert_eq!(usage.delta, 0.);
+
+        // without a delta budget, nothing is gained over basic composition
+        let usage = compose_optimal(&usages(10, 0.1, 0.), 0.).unwrap();
+        assert!((usage.epsilon - 1.).abs() < 1e-12);
+        assert_eq!(usage.delta, 0.);
+
+        // the deltas of the mechanisms must fit within the budget
+        assert!(compose_optimal(&usages(10, 0.1, 1e-5), 1e-5).is_none());
+    }
+
+    #[test]
+    fn renyi_divergence_laplace() {
+        // https://arxiv.org/pdf/1702.07476.pdf, table 2, at order 2 and scale 1
+        let expected = ((2. / 3.) * 1_f64.exp() + (1. / 3.) * (-2_f64).exp()).ln();
+        assert!((renyi_divergence(&Noise::Laplace {scale: 1.}, 2.) - expected).abs() < 1e-12);
+
+        // the divergence of an epsilon-DP mechanism is at most epsilon at every order
+        for alpha in RENYI_ORDERS.iter() {
+            assert!(renyi_divergence(&Noise::Laplace {scale: 2.}, *alpha) <= 0.5 + 1e-12);
+        }
+        // large orders do not overflow
+        assert!(renyi_divergence(&Noise::Laplace {scale: 0.01}, 256.).is_finite());
+    }
+
+    #[test]
+    fn compose_renyi_gaussian() {
+        // the Gaussian divergence is alpha / (2 sigma^2), so k mechanisms compose to the minimum over the grid
+        let noises = vec![Noise::Gaussian {scale: 10.}; 100];
+        let expected = RENYI_ORDERS.iter()
+            .map(|alpha| 100. * alpha / 200. + (1e5_f64).ln() / (alpha - 1.))
+            .fold(f64::INFINITY, f64::min);
+        let usage = compose_renyi(&noises, 1e-5).unwrap();
+        assert!((usage.epsilon - expected).abs() < 1e-12);
+        assert_eq!(usage.delta, 1e-5);
+    }
+
+    #[test]
+    fn compose_renyi_edge_cases() {
+        assert!(compose_renyi(&[Noise::Laplace {scale: 1.}], 0.).is_none());
+        assert!(compose_renyi(&[], 1e-5).is_none());
+
+        // a single mechanism is never charged more than its divergence at the best order
+        let usage = compose_renyi(&[Noise::Pure {epsilon: 1.}], 1e-5).unwrap();
+        assert!(usage.epsilon > 1.);
+        assert!(usage.epsilon <= 1. + (1e5_f64).ln() / 255. + 1e-12);
+    }
+
+    #[test]
+    fn compose_concentrated_conversion() {
+        // https://arxiv.org/pdf/1605.02065.pdf, proposition 1.3: rho-zCDP is (rho + 2 sqrt(rho ln(1 / delta)), delta)-DP
+        let usage = compose_concentrated(&[Noise::Gaussian {scale: 1.}], 1e-5).unwrap();
+        assert!((usage.rho - 0.5).abs() < 1e-12);
+        assert!((usage.epsilon - (0.5 + 2. * (0.5 * (1e5_f64).ln()).sqrt())).abs() < 1e-12);
+        assert!((usage.epsilon - 5.29852).abs() < 1e-4);
+
+        // rho is additive, and epsilon-DP mechanisms are (epsilon^2 / 2)-zCDP
+        let usage = compose_concentrated(&[Noise::Gaussian {scale: 1.}, Noise::Laplace {scale: 1.}, Noise::Pure {epsilon: 1.}], 1e-5).unwrap();
+        assert!((usage.rho - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn compose_concentrated_edge_cases() {
+        assert!(compose_concentrated(&[Noise::Gaussian {scale: 1.}], 0.).is_none());
+        assert!(compose_concentrated(&[], 1e-5).is_none());
+    }
+
+    #[test]
+    fn gaussian_delta_tradeoff() {
+        // https://arxiv.org/pdf/1905.02383.pdf, corollary 2.13: at epsilon 0, delta is the total variation 2 Phi(mu / 2) - 1
+        assert!((gaussian_delta(1., 0.) - 0.382925).abs() < 1e-6);
+        // delta decreases in epsilon
+        assert!(gaussian_delta(1., 1.) < gaussian_delta(1., 0.5));
+    }
+
+    #[test]
+    fn compose_gaussian_root_sum_of_squares() {
+        // four 0.5-GDP mechanisms compose to 1-GDP
+        let usage = compose_gaussian(&vec![Noise::Gaussian {scale: 2.}; 4], 1e-5).unwrap();
+        assert!((usage.mu - 1.).abs() < 1e-12);
+        // the epsilon is the smallest at which the delta is within the bound
+        assert!((gaussian_delta(1., usage.epsilon) - 1e-5).abs() < 1e-9);
+
+        let single = compose_gaussian(&[Noise::Gaussian {scale: 1.}], 1e-5).unwrap();
+        assert!((single.epsilon - usage.epsilon).abs() < 1e-9);
+    }
+
+    #[test]
+    fn compose_gaussian_edge_cases() {
+        assert!(compose_gaussian(&[Noise::Gaussian {scale: 1.}], 0.).is_none());
+        assert!(compose_gaussian(&[], 1e-5).is_none());
+        // any non-Gaussian mechanism rules out Gaussian DP accounting
+        assert!(compose_gaussian(&[Noise::Gaussian {scale: 1.}, Noise::Laplace {scale: 1.}], 1e-5).is_none());
+    }
+
+    fn gaussian_analysis(distance: yarrow::privacy_definition::Distance, delta: f64) -> yarrow::Analysis {
+        let mean = component(Dpmean(yarrow::DpMean {epsilon: 1., delta, mechanism: yarrow::Mechanism::Gaussian as i32}), &[]);
+        yarrow::Analysis {
+            privacy_definition: Some(yarrow::PrivacyDefinition {distance: distance as i32, delta: 1e-5, ..Default::default()}),
+            ..analysis_from(vec![(1, mean)])
+        }
+    }
+
+    #[test]
+    fn validate_mechanisms_gaussian_distances() {
+        use yarrow::privacy_definition::Distance;
+        for distance in &[Distance::Approximate, Distance::Renyi, Distance::Concentrated, Distance::F] {
+            assert!(validate_mechanisms(&gaussian_analysis(*distance, 1e-5)).is_empty(), "{:?}", distance);
+        }
+        let diagnostics = validate_mechanisms(&gaussian_analysis(Distance::Pure, 1e-5));
+        assert_eq!(codes(&diagnostics), vec!["MECHANISM_UNSUPPORTED"]);
+        assert!(diagnostics[0].message.contains("APPROXIMATE, RENYI, CONCENTRATED, F"));
+    }
+
+    #[test]
+    fn validate_mechanisms_gaussian_delta() {
+        use yarrow::privacy_definition::Distance;
+        for delta in &[0., 1., -1e-5] {
+            assert_eq!(codes(&validate_mechanisms(&gaussian_analysis(Distance::Approximate, *delta))), vec!["INVALID_DELTA"]);
+        }
+    }
+
+    #[test]
+    fn validate_mechanisms_epsilon() {
+        let privatizers = |epsilon: f64| vec![
+            Dpmean(yarrow::DpMean {epsilon, ..Default::default()}),
+            Dpvariance(yarrow::DpVariance {epsilon, ..Default::default()}),
+            Dpmomentraw(yarrow::DpMomentRaw {epsilon, ..Default::default()}),
+            Dpcovariance(yarrow::DpCovariance {epsilon, ..Default::default()}),
+            Exponentialmechanism(yarrow::ExponentialMechanism {epsilon, ..Default::default()})];
+        let validate = |privatizer| validate_mechanisms(&analysis_from(vec![(1, component(privatizer, &[]))]));
+
+        for epsilon in &[0., -1., f64::INFINITY, f64::NAN] {
+            for privatizer in privatizers(*epsilon) {
+                let name = components::get_component(&privatizer).name();
+                assert_eq!(codes(&validate(privatizer)), vec!["INVALID_EPSILON"], "{} at {}", name, epsilon);
+            }
+        }
+        for privatizer in privatizers(0.5) {
+            assert!(validate(privatizer).is_empty());
+        }
+    }
+
+    #[test]
+    fn gaussian_scale_matches_balle_wang() {
+        // https://arxiv.org/pdf/1805.06530.pdf, the analytic Gaussian mechanism at epsilon 1 and delta 1e-5
+        let scale = get_gaussian_scale(1., 1e-5);
+        assert!((scale - 3.7306).abs() < 1e-3);
+        assert!((gaussian_delta(1. / scale, 1.) - 1e-5).abs() < 1e-9);
+        // tighter than the classical calibration sqrt(2 ln(1.25 / delta)) / epsilon
+        assert!(scale < (2. * (1.25_f64 / 1e-5).ln()).sqrt());
+        // and still valid for epsilon above one
+        let scale = get_gaussian_scale(5., 1e-5);
+        assert!((gaussian_delta(1. / scale, 5.) - 1e-5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gaussian_scale_edge_cases() {
+        assert_eq!(get_gaussian_scale(1., 0.), f64::INFINITY);
+        assert_eq!(get_gaussian_scale(0., 1e-5), f64::INFINITY);
+        assert_eq!(get_gaussian_scale(1., 1.), 0.);
+    }
+}
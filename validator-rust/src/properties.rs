@@ -0,0 +1,496 @@
+use crate::yarrow;
+use crate::base;
+use crate::privacy;
+
+use std::collections::HashMap;
+
+// statically known properties of the output of a node
+#[derive(Clone, Debug, Default)]
+pub struct Properties {
+    // the node depends on the private data, and has not been privatized
+    pub private: bool,
+    pub datatype: Option<yarrow::DataType>,
+    // length of each axis, if every length is known
+    pub shape: Option<Vec<u64>>,
+    // bounds on every element
+    pub bounds: Option<(f64, f64)>,
+    // set of values every element is drawn from
+    pub categories: Option<yarrow::ArrayNd>,
+    // nulls or NaNs may be present
+    pub nullable: bool,
+    // length of the first axis, if public
+    pub num_records: Option<u64>
+}
+
+fn is_numeric(datatype: &yarrow::DataType) -> bool {
+    matches!(datatype, yarrow::DataType::Bool | yarrow::DataType::I64 | yarrow::DataType::F64)
+}
+
+fn parse_datatype(value: &str) -> Option<yarrow::DataType> {
+    match value {
+        "BYTES" => Some(yarrow::DataType::Bytes),
+        "BOOL" => Some(yarrow::DataType::Bool),
+        "I64" => Some(yarrow::DataType::I64),
+        "F64" => Some(yarrow::DataType::F64),
+        "STRING" => Some(yarrow::DataType::String),
+        _ => None
+    }
+}
+
+fn get_bounds(values: &[f64]) -> Option<(f64, f64)> {
+    let values: Vec<&f64> = values.iter().filter(|value| !value.is_nan()).collect();
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().fold((f64::INFINITY, f64::NEG_INFINITY),
+                            |(lower, upper), value| (lower.min(**value), upper.max(**value))))
+}
+
+// properties of a public array, such as a literal or a released value
+pub fn get_array_properties(value: &yarrow::ArrayNd) -> Properties {
+    let values = base::get_vec_f64(value);
+    Properties {
+        private: false,
+        datatype: yarrow::DataType::from_i32(value.datatype),
+        shape: Some(value.shape.clone()),
+        bounds: values.as_ref().and_then(|values| get_bounds(values)),
+        categories: Some(value.clone()),
+        nullable: values.map(|values| values.iter().any(|value| value.is_nan())).unwrap_or(false),
+        num_records: value.shape.first().cloned()
+    }
+}
+
+// narrow the properties of a node with public constraints on its output
+pub fn constrain(properties: Properties, constraint: &yarrow::Constraint) -> Properties {
+    let minimum = constraint.minimum.as_ref().and_then(base::get_f64);
+    let maximum = constraint.maximum.as_ref().and_then(base::get_f64);
+    let (lower, upper) = properties.bounds.unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+    let bounds = (minimum.map(|minimum| minimum.max(lower)).unwrap_or(lower),
+                  maximum.map(|maximum| maximum.min(upper)).unwrap_or(upper));
+
+    Properties {
+        bounds: match bounds.0.is_finite() && bounds.1.is_finite() {
+            true => Some(bounds),
+            false => properties.bounds
+        },
+        num_records: constraint.num_records.as_ref().and_then(base::get_f64)
+            .map(|num_records| num_records as u64).or(properties.num_records),
+        // values drawn from a declared set cannot be null
+        nullable: properties.nullable && constraint.categories.is_none(),
+        categories: constraint.categories.clone().or(properties.categories),
+        ..properties
+    }
+}
+
+// interval arithmetic for elementwise operations on bounded data
+fn add_bounds(left: (f64, f64), right: (f64, f64)) -> (f64, f64) {
+    (left.0 + right.0, left.1 + right.1)
+}
+
+fn multiply_bounds(left: (f64, f64), right: (f64, f64)) -> (f64, f64) {
+    let products = [left.0 * right.0, left.0 * right.1, left.1 * right.0, left.1 * right.1];
+    get_bounds(&products).unwrap_or((f64::NEG_INFINITY, f64::INFINITY))
+}
+
+fn negate_bounds(bounds: (f64, f64)) -> (f64, f64) {
+    (-bounds.1, -bounds.0)
+}
+
+fn reciprocal_bounds(bounds: (f64, f64)) -> Option<(f64, f64)> {
+    match bounds.0 > 0. || bounds.1 < 0. {
+        true => Some((1. / bounds.1, 1. / bounds.0)),
+        false => None
+    }
+}
+
+// infer the properties of one node, given the properties of its arguments
+pub fn infer(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release,
+             properties: &HashMap<u32, Properties>) -> Properties {
+    use yarrow::component::Value::*;
+
+    // released values are public, regardless of how they were computed
+    if let Some(value) = release.values.get(&node_id)
+        .and_then(|node_release| node_release.values.get("data")) {
+        return get_array_properties(value);
+    }
+
+    let component = match analysis.graph.get(&node_id) {
+        Some(component) => component,
+        None => return Properties::default()
+    };
+    let argument = |name: &str| component.arguments.get(name)
+        .and_then(|field| properties.get(&field.source_node_id))
+        .cloned().unwrap_or_default();
+    let public_scalar = |name: &str| match argument(name).private {
+        true => None,
+        false => base::get_argument_f64(node_id, name, analysis, release)
+    };
+
+    // elementwise operations broadcast a scalar against the other operand
+    let elementwise = |names: &[&str]| {
+        let operands: Vec<Properties> = names.iter().map(|name| argument(name)).collect();
+        let datatypes: Vec<Option<yarrow::DataType>> = operands.iter().map(|operand| operand.datatype).collect();
+        let broadcast = operands.iter()
+            .find(|operand| operand.shape.as_ref().map(|shape| !shape.is_empty()).unwrap_or(true))
+            .or_else(|| operands.first()).cloned().unwrap_or_default();
+        Properties {
+            private: operands.iter().any(|operand| operand.private),
+            datatype: match datatypes.iter().all(|datatype| *datatype == datatypes[0]) {
+                true => datatypes[0],
+                false => None
+            },
+            shape: broadcast.shape,
+            bounds: None,
+            categories: None,
+            nullable: operands.iter().any(|operand| operand.nullable),
+            num_records: broadcast.num_records
+        }
+    };
+
+    // aggregations release a single value
+    let aggregate = |data: &Properties| Properties {
+        private: data.private,
+        datatype: Some(yarrow::DataType::F64),
+        shape: Some(vec![]),
+        bounds: None,
+        categories: None,
+        nullable: data.nullable,
+        num_records: None
+    };
+
+    match component.value.as_ref() {
+        Some(Literal(x)) => x.value.as_ref().map(get_array_properties).unwrap_or_default(),
+        Some(Constant(_x)) => Properties::default(),
+
+        Some(Datasource(x)) => {
+            let datatype = base::get_argument_value(node_id, "datatype", analysis, release)
+                .and_then(|value| match value.data.as_ref() {
+                    Some(yarrow::array_nd::Data::String(x)) => x.data.first().and_then(|value| parse_datatype(value)),
+                    _ => None
+                })
+                // the default datatype marks the field as unset
+                .or_else(|| match x.datatype {
+                    0 => None,
+                    datatype => yarrow::DataType::from_i32(datatype)
+                });
+            let constraints = x.constraints.as_ref().map(get_array_properties);
+            Properties {
+                private: true,
+                datatype,
+                shape: None,
+                bounds: constraints.as_ref().and_then(|constraints| constraints.bounds),
+                categories: x.constraints.clone(),
+                // floats may be parsed as NaN, unless the values are constrained
+                nullable: constraints.is_none() && datatype == Some(yarrow::DataType::F64),
+                num_records: None
+            }
+        },
+
+        Some(Clip(_x)) => {
+            let data = argument("data");
+            Properties {
+                datatype: Some(yarrow::DataType::F64),
+                bounds: match (public_scalar("minimum"), public_scalar("maximum")) {
+                    (Some(minimum), Some(maximum)) if minimum <= maximum => Some(match data.bounds {
+                        // clamping data that is already bounded can only tighten its bounds
+                        Some((lower, upper)) => (lower.max(minimum).min(maximum), upper.max(minimum).min(maximum)),
+                        None => (minimum, maximum)
+                    }),
+                    _ => None
+                },
+                categories: None,
+                ..data
+            }
+        },
+
+        Some(Bin(_x)) => Properties {
+            datatype: Some(yarrow::DataType::String),
+            bounds: None,
+            categories: None,
+            nullable: false,
+            ..argument("data")
+        },
+
+        Some(Negate(_x)) => {
+            let data = argument("data");
+            Properties {bounds: data.bounds.map(negate_bounds), categories: None, ..data}
+        },
+        Some(Add(_x)) => Properties {
+            bounds: argument("left").bounds.and_then(|left| argument("right").bounds
+                .map(|right| add_bounds(left, right))),
+            ..elementwise(&["left", "right"])
+        },
+        Some(Subtract(_x)) => Properties {
+            bounds: argument("left").bounds.and_then(|left| argument("right").bounds
+                .map(|right| add_bounds(left, negate_bounds(right)))),
+            ..elementwise(&["left", "right"])
+        },
+        Some(Multiply(_x)) => Properties {
+            bounds: argument("left").bounds.and_then(|left| argument("right").bounds
+                .map(|right| multiply_bounds(left, right))),
+            ..elementwise(&["left", "right"])
+        },
+        Some(Divide(_x)) => {
+            let reciprocal = argument("right").bounds.and_then(reciprocal_bounds);
+            let operands = elementwise(&["left", "right"]);
+            Properties {
+                bounds: argument("left").bounds.and_then(|left| reciprocal
+                    .map(|right| multiply_bounds(left, right))),
+                // division by zero is only ruled out when the divisor excludes zero
+                nullable: operands.nullable || (reciprocal.is_none() && operands.datatype == Some(yarrow::DataType::F64)),
+                ..operands
+            }
+        },
+        Some(Power(_x)) => Properties {
+            datatype: Some(yarrow::DataType::F64),
+            nullable: true,
+            ..elementwise(&["left", "right"])
+        },
+
+        Some(Laplace(_x)) => Properties {
+            bounds: None,
+            categories: None,
+            ..argument("data")
+        },
+
+        Some(Sum(_x)) => {
+            let data = argument("data");
+            Properties {
+                bounds: data.bounds.and_then(|(lower, upper)| data.num_records
+                    .map(|num_records| (lower * num_records as f64, upper * num_records as f64))),
+                ..aggregate(&data)
+            }
+        },
+        Some(Mean(_)) | Some(Median(_)) => {
+            let data = argument("data");
+            Properties {bounds: data.bounds, ..aggregate(&data)}
+        },
+
+        // one count for each category of the grouping
+        Some(Count(_x)) => {
+            let group_by = argument("group_by");
+            let data = argument("data");
+            Properties {
+                private: data.private || group_by.private,
+                datatype: Some(yarrow::DataType::F64),
+                shape: group_by.categories.as_ref().map(|categories| vec![base::get_len(categories)]),
+                bounds: data.num_records.map(|num_records| (0., num_records as f64)),
+                categories: None,
+                nullable: false,
+                num_records: None
+            }
+        },
+
+        // privatized outputs may be released
+        Some(Dpmean(_)) | Some(Dpvariance(_)) | Some(Dpmomentraw(_)) => Properties {
+            private: false,
+            ..aggregate(&argument("data"))
+        },
+        Some(Dpcovariance(_x)) => Properties {
+            private: false,
+            nullable: argument("data_x").nullable || argument("data_y").nullable,
+            ..aggregate(&argument("data_x"))
+        },
+        // one of the public candidates
+        Some(Exponentialmechanism(_x)) => {
+            let candidates = argument("candidates");
+            Properties {
+                private: false,
+                datatype: candidates.datatype,
+                shape: Some(vec![]),
+                bounds: candidates.bounds,
+                // the output is always one of the candidates
+                categories: base::get_argument_value(node_id, "candidates", analysis, release).cloned()
+                    .or(candidates.categories),
+                nullable: false,
+                num_records: None
+            }
+        },
+
+        // nothing is known about the output, other than that it depends on any private argument
+        Some(Custom(_x)) => Properties {
+            private: component.arguments.values()
+                .filter_map(|field| properties.get(&field.source_node_id))
+                .any(|argument| argument.private),
+            nullable: true,
+            ..Properties::default()
+        },
+
+        None => Properties::default()
+    }
+}
+
+pub fn infer_properties(analysis: &yarrow::Analysis, release: &yarrow::Release) -> HashMap<u32, Properties> {
+    let mut properties = HashMap::<u32, Properties>::new();
+    // an invalid graph has no well-defined properties
+    if let Ok(traversal) = base::get_traversal(analysis) {
+        for node_id in traversal {
+            let node_properties = infer(node_id, analysis, release, &properties);
+            properties.insert(node_id, node_properties);
+        }
+    }
+    properties
+}
+
+// arguments that must be numeric for the runtime to evaluate a component
+fn get_numeric_arguments(value: &yarrow::component::Value) -> Vec<&'static str> {
+    use yarrow::component::Value::*;
+    match value {
+        Add(_) | Subtract(_) | Divide(_) | Multiply(_) | Power(_) => vec!["left", "right"],
+        Negate(_) | Laplace(_) | Mean(_) | Median(_) | Sum(_) => vec!["data"],
+        Clip(_x) => vec!["data", "minimum", "maximum"],
+        Bin(_x) => vec!["data", "edges"],
+        Dpmean(_) | Dpvariance(_) | Dpmomentraw(_) => vec!["data", "num_records", "minimum", "maximum"],
+        Dpcovariance(_x) => vec!["data_x", "data_y", "num_records",
+                                 "minimum_x", "minimum_y", "maximum_x", "maximum_y"],
+        _ => vec![]
+    }
+}
+
+// check the inferred properties against what each component requires of its arguments
+pub fn validate_properties(analysis: &yarrow::Analysis) -> Vec<yarrow::Diagnostic> {
+    use yarrow::component::Value::*;
+    let properties = infer_properties(analysis, &yarrow::Release::default());
+    let mut diagnostics = Vec::new();
+
+    let mut node_ids: Vec<&u32> = analysis.graph.keys().collect();
+    node_ids.sort();
+
+    for node_id in node_ids {
+        let component = analysis.graph.get(node_id).unwrap();
+        let value = match component.value.as_ref() {
+            Some(value) => value,
+            None => continue
+        };
+        let argument = |name: &str| component.arguments.get(name)
+            .and_then(|field| properties.get(&field.source_node_id))
+            .cloned().unwrap_or_default();
+
+        for name in get_numeric_arguments(value) {
+            if let Some(datatype) = argument(name).datatype {
+                if !is_numeric(&datatype) {
+                    diagnostics.push(base::error(*node_id, name, "NON_NUMERIC",
+                                                 format!("node {}: argument \"{}\" must be numeric, but is {:?}", node_id, name, datatype)));
+                }
+            }
+        }
+
+        match value {
+            Add(_) | Subtract(_) | Divide(_) | Multiply(_) => if let (Some(left), Some(right)) = (argument("left").datatype, argument("right").datatype) {
+                if left != right {
+                    diagnostics.push(base::error(*node_id, "", "MISMATCHED_TYPES",
+                                                 format!("node {}: cannot combine {:?} with {:?}", node_id, left, right)));
+                }
+            },
+            Exponentialmechanism(x) => {
+                if let (Some(data), Some(candidates)) = (argument("data").datatype, argument("candidates").datatype) {
+                    if data != candidates {
+                        diagnostics.push(base::error(*node_id, "candidates", "MISMATCHED_TYPES",
+                                                     format!("node {}: cannot compare {:?} candidates with {:?} data", node_id, candidates, data)));
+                    }
+                }
+                // candidates for the median are ranked against the data, which the runtime only does for numbers
+                if x.utility == yarrow::exponential_mechanism::Utility::Median as i32 {
+                    if let Some(data) = argument("data").datatype {
+                        if !matches!(data, yarrow::DataType::F64 | yarrow::DataType::I64) {
+                            diagnostics.push(base::error(*node_id, "data", "UNSUPPORTED_DATATYPE",
+                                                         format!("node {}: the MEDIAN utility requires F64 or I64 data, but the data is {:?}", node_id, data)));
+                        }
+                    }
+                }
+            },
+            _ => ()
+        }
+
+        let bound_pairs: &[(&str, &str)] = match value {
+            Clip(_) | Dpmean(_) | Dpvariance(_) | Dpmomentraw(_) => &[("minimum", "maximum")],
+            Dpcovariance(_) => &[("minimum_x", "maximum_x"), ("minimum_y", "maximum_y")],
+            _ => &[]
+        };
+        for (minimum_name, maximum_name) in bound_pairs {
+            let minimum = base::get_argument_f64(*node_id, minimum_name, analysis, &yarrow::Release::default());
+            let maximum = base::get_argument_f64(*node_id, maximum_name, analysis, &yarrow::Release::default());
+            if let (Some(minimum), Some(maximum)) = (minimum, maximum) {
+                if minimum > maximum {
+                    diagnostics.push(base::error(*node_id, minimum_name, "INVALID_BOUNDS",
+                                                 format!("node {}: \"{}\" ({}) exceeds \"{}\" ({})",
+                                                         node_id, minimum_name, minimum, maximum_name, maximum)));
+                }
+            }
+        }
+
+        // NaN survives clamping and noise, so its presence would be revealed by the release
+        if privacy::is_privatizer(component) {
+            let data_names: &[&str] = match value {
+                Dpcovariance(_) => &["data_x", "data_y"],
+                _ => &["data"]
+            };
+            for name in data_names {
+                if argument(name).nullable {
+                    diagnostics.push(base::warning(*node_id, name, "NULLABLE_DATA",
+                                                   format!("node {}: argument \"{}\" may contain nulls or NaNs", node_id, name)));
+                }
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    #[test]
+    fn exponential_mechanism_categories() {
+        let candidates = yarrow::ArrayNd {
+            datatype: yarrow::DataType::String as i32,
+            data: Some(yarrow::array_nd::Data::String(yarrow::Array1Dstr {data: vec!["a".to_string(), "b".to_string()]})),
+            order: vec![0],
+            shape: vec![2]
+        };
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::String)),
+            (2, component(Literal(yarrow::Literal {value: Some(candidates.clone())}), &[])),
+            (3, component(Exponentialmechanism(yarrow::ExponentialMechanism {epsilon: 1., ..Default::default()}),
+                          &[("data", 1), ("candidates", 2)]))]);
+
+        let properties = infer_properties(&analysis, &yarrow::Release::default());
+        assert!(!properties[&3].private);
+        assert_eq!(properties[&3].categories, Some(candidates));
+    }
+
+    #[test]
+    fn custom_output_is_private_if_any_argument_is() {
+        let custom = |arguments: &[(&str, u32)]| component(Custom(yarrow::Custom {name: "Clamp".to_string()}), arguments);
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, literal(1.)),
+            (3, custom(&[("data", 1), ("bound", 2)])),
+            (4, custom(&[("bound", 2)]))]);
+
+        let properties = infer_properties(&analysis, &yarrow::Release::default());
+        assert!(properties[&3].private);
+        assert!(!properties[&4].private);
+    }
+
+    #[test]
+    fn median_utility_requires_numbers() {
+        let median = |datatype| analysis_from(vec![
+            (1, datasource(datatype)),
+            (2, component(Literal(yarrow::Literal {value: Some(yarrow::ArrayNd {datatype: datatype as i32, ..Default::default()})}), &[])),
+            (3, component(Exponentialmechanism(yarrow::ExponentialMechanism {
+                epsilon: 1., utility: yarrow::exponential_mechanism::Utility::Median as i32
+            }), &[("data", 1), ("candidates", 2)]))]);
+
+        let errors = |datatype| validate_properties(&median(datatype)).into_iter()
+            .filter(base::is_error).map(|diagnostic| diagnostic.code).collect::<Vec<String>>();
+        for datatype in &[yarrow::DataType::F64, yarrow::DataType::I64] {
+            assert!(errors(*datatype).is_empty(), "{:?}", datatype);
+        }
+        for datatype in &[yarrow::DataType::Bool, yarrow::DataType::String] {
+            assert_eq!(errors(*datatype), vec!["UNSUPPORTED_DATATYPE"], "{:?}", datatype);
+        }
+    }
+}
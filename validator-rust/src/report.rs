@@ -0,0 +1,188 @@
+use crate::yarrow;
+use crate::accuracy;
+use crate::base;
+use crate::components;
+use crate::privacy;
+use crate::sensitivity;
+use crate::snapping;
+
+use std::collections::{BTreeSet, HashSet};
+use serde_json::{json, Value};
+
+// significance levels at which the accuracy of each release is reported
+pub const REPORT_ALPHAS: [f64; 3] = [0.01, 0.05, 0.1];
+
+fn array_to_json(value: &yarrow::ArrayNd) -> Value {
+    use yarrow::array_nd::Data;
+    let data = match value.data.as_ref() {
+        Some(Data::Bytes(x)) => json!(x),
+        Some(Data::Bool(x)) => json!(x.data),
+        Some(Data::I64(x)) => json!(x.data),
+        Some(Data::F64(x)) => json!(x.data),
+        Some(Data::String(x)) => json!(x.data),
+        None => Value::Null
+    };
+    json!({"data": data, "shape": value.shape})
+}
+
+fn metadata_to_json(metadata: &Option<yarrow::Metadata>) -> Value {
+    let metadata = metadata.clone().unwrap_or_default();
+    json!({"about": metadata.about, "license": metadata.license})
+}
+
+// dataset and column of every data source the node depends on
+fn get_columns(node_id: u32, analysis: &yarrow::Analysis) -> Vec<Value> {
+    let mut columns = BTreeSet::<(String, String)>::new();
+    let mut visited = HashSet::<u32>::new();
+    let mut node_ids = vec![node_id];
+
+    while let Some(node_id) = node_ids.pop() {
+        if !visited.insert(node_id) {
+            continue;
+        }
+        if let Some(component) = analysis.graph.get(&node_id) {
+            if let Some(yarrow::component::Value::Datasource(x)) = component.value.as_ref() {
+                columns.insert((x.dataset_id.clone(), x.column_id.clone()));
+            }
+            node_ids.extend(component.arguments.values().map(|field| field.source_node_id));
+        }
+    }
+    columns.into_iter()
+        .map(|(dataset_id, column_id)| json!({"dataset_id": dataset_id, "column_id": column_id}))
+        .collect()
+}
+
+// name of an enum value as it appears in the protos, e.g. GaussianDp -> GAUSSIAN_DP
+fn to_proto_name(value: impl std::fmt::Debug) -> String {
+    let name = format!("{:?}", value);
+    name.chars().enumerate()
+        .flat_map(|(i, c)| match i > 0 && c.is_uppercase() {
+            true => vec!['_', c],
+            false => vec![c.to_ascii_uppercase()]
+        })
+        .collect()
+}
+
+fn get_mechanism(component: &yarrow::Component) -> Option<String> {
+    use yarrow::component::Value::*;
+    yarrow::Mechanism::from_i32(match component.value.as_ref()? {
+        Dpmean(x) => x.mechanism,
+        Dpvariance(x) => x.mechanism,
+        Dpmomentraw(x) => x.mechanism,
+        Dpcovariance(x) => x.mechanism,
+        // selection is its own mechanism, rather than a choice of noise
+        Exponentialmechanism(_) => return Some("EXPONENTIAL".to_string()),
+        _ => return None
+    }).map(to_proto_name)
+}
+
+fn release_node_to_json(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release,
+                        privacy_usage: &yarrow::PrivacyUsage) -> Value {
+    let component = analysis.graph.get(&node_id);
+    let usage = privacy_usage.values.get(&node_id);
+    let sensitivity = sensitivity::get_sensitivity(node_id, analysis, release);
+    let noise = component.and_then(privacy::get_noise);
+
+    let noise_scale = match (&noise, sensitivity) {
+        (Some(privacy::Noise::Laplace {scale}), Some(sensitivity)) => Some(scale * sensitivity),
+        (Some(privacy::Noise::Gaussian {scale}), Some(sensitivity)) => Some(scale * sensitivity),
+        (Some(privacy::Noise::Snapping {epsilon}), Some(sensitivity)) => snapping::get_bound(node_id, analysis, release)
+            .map(|bound| sensitivity / snapping::get_epsilon_prime(*epsilon, bound, sensitivity)),
+        _ => None
+    };
+    let accuracies: Vec<Value> = REPORT_ALPHAS.iter()
+        .filter_map(|alpha| accuracy::get_node_accuracy(node_id, analysis, release, *alpha)
+            .map(|value| json!({"alpha": alpha, "value": value})))
+        .collect();
+    let field = |name: &str| release.values.get(&node_id)
+        .and_then(|node_release| node_release.values.get(name));
+
+    json!({
+        "node_id": node_id,
+        "statistic": component.and_then(|component| component.value.as_ref()).map(|value| components::get_component(value).name()),
+        "columns": get_columns(node_id, analysis),
+        "mechanism": component.and_then(get_mechanism),
+        "epsilon": usage.map(|usage| usage.epsilon),
+        "delta": usage.map(|usage| usage.delta),
+        "sensitivity": sensitivity,
+        "noise_scale": noise_scale,
+        "accuracy": accuracies,
+        // effective epsilon of the snapping mechanism, as reported by the runtime
+        "epsilon_prime": field("epsilon_prime").and_then(base::get_f64),
+        "value": field("data").map(array_to_json)
+    })
+}
+
+// describe every released node, along with the privacy spent and the provenance of the data
+pub fn generate_report(analysis: &yarrow::Analysis, release: &yarrow::Release, dataset: &yarrow::Dataset) -> Value {
+    let privacy_usage = privacy::compute_privacy_usage(analysis);
+    let total = privacy_usage.total.clone().unwrap_or_default();
+
+    // literals are public inputs, not releases
+    let mut node_ids: Vec<u32> = release.values.keys().cloned()
+        .filter(|node_id| !matches!(analysis.graph.get(node_id).and_then(|component| component.value.as_ref()),
+                                    Some(yarrow::component::Value::Literal(_))))
+        .collect();
+    node_ids.sort();
+
+    let mut table_names: Vec<&String> = dataset.tables.keys().collect();
+    table_names.sort();
+
+    json!({
+        "dataset": {
+            "metadata": metadata_to_json(&dataset.metadata),
+            "tables": table_names.into_iter()
+                .map(|name| json!({"name": name, "metadata": metadata_to_json(&dataset.tables[name].metadata)}))
+                .collect::<Vec<Value>>()
+        },
+        "privacy_usage": {
+            "epsilon": total.epsilon,
+            "delta": total.delta,
+            "rho": total.rho,
+            "composition": yarrow::Composition::from_i32(privacy_usage.composition)
+                .map(to_proto_name)
+        },
+        "releases": node_ids.into_iter()
+            .map(|node_id| release_node_to_json(node_id, analysis, release, &privacy_usage))
+            .collect::<Vec<Value>>()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    fn released(values: Vec<(u32, f64)>) -> yarrow::Release {
+        yarrow::Release {values: values.into_iter()
+            .map(|(node_id, value)| (node_id, yarrow::ReleaseNode {
+                values: vec![("data".to_string(), scalar(value))].into_iter().collect()
+            }))
+            .collect()}
+    }
+
+    #[test]
+    fn report_describes_releases() {
+        let dp_mean = yarrow::DpMean {epsilon: 0.5, mechanism: yarrow::Mechanism::Laplace as i32, ..Default::default()};
+        let age = yarrow::DataSource {dataset_id: "PUMS".to_string(), column_id: "age".to_string(), ..Default::default()};
+        let analysis = analysis_from(vec![
+            (1, component(Datasource(age), &[])),
+            (2, literal(0.)), (3, literal(100.)), (4, literal(1000.)),
+            (5, component(Dpmean(dp_mean), &[("data", 1), ("minimum", 2), ("maximum", 3), ("num_records", 4)]))]);
+        let release = released(vec![(2, 0.), (3, 100.), (4, 1000.), (5, 41.5)]);
+
+        let report = generate_report(&analysis, &release, &yarrow::Dataset::default());
+        assert_eq!(report["privacy_usage"]["epsilon"], json!(0.5));
+
+        // literals are public inputs, so only the privatized mean is described
+        let releases = report["releases"].as_array().unwrap();
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0]["node_id"], json!(5));
+        assert_eq!(releases[0]["mechanism"], json!("LAPLACE"));
+        assert_eq!(releases[0]["epsilon"], json!(0.5));
+        assert_eq!(releases[0]["columns"], json!([{"dataset_id": "PUMS", "column_id": "age"}]));
+        assert_eq!(releases[0]["value"]["data"], json!([41.5]));
+        assert_eq!(releases[0]["accuracy"].as_array().unwrap().len(), REPORT_ALPHAS.len());
+    }
+}
@@ -0,0 +1,386 @@
+use crate::yarrow;
+use crate::base;
+use crate::privacy;
+use crate::properties;
+
+use std::collections::HashMap;
+
+// Each statistic is estimated by dividing by the public num_records, rather than the size of the data,
+// so that the sensitivity does not depend on the private number of records.
+// Under ADD_REMOVE, neighboring datasets differ by the presence of one record.
+// Under SUBSTITUTE, neighboring datasets differ by the value of one record.
+
+fn get_neighboring(definition: &yarrow::PrivacyDefinition) -> yarrow::privacy_definition::Neighboring {
+    yarrow::privacy_definition::Neighboring::from_i32(definition.neighboring)
+        .unwrap_or(yarrow::privacy_definition::Neighboring::Substitute)
+}
+
+// neighboring datasets differ in group_size records, so the sensitivity of an individual is scaled
+fn scale_by_group(sensitivity: f64, definition: &yarrow::PrivacyDefinition) -> f64 {
+    sensitivity * f64::from(privacy::get_group_size(definition))
+}
+
+// sensitivity of the clamped sum divided by num_records
+pub fn dp_mean(definition: &yarrow::PrivacyDefinition, num_records: f64, minimum: f64, maximum: f64) -> f64 {
+    use yarrow::privacy_definition::Neighboring;
+    let sensitivity = match get_neighboring(definition) {
+        Neighboring::Substitute => (maximum - minimum) / num_records,
+        Neighboring::AddRemove => minimum.abs().max(maximum.abs()) / num_records
+    };
+    scale_by_group(sensitivity, definition)
+}
+
+// sensitivity of the clamped sum of squared deviations divided by num_records
+// The deviations are about the mean of the m records actually present, which need not be num_records.
+// A substitution changes their sum of squared deviations by at most (m - 1) / m * range^2 < range^2,
+// so range^2 / num_records bounds the change without depending on the private m.
+pub fn dp_variance(definition: &yarrow::PrivacyDefinition, num_records: f64, minimum: f64, maximum: f64) -> f64 {
+    use yarrow::privacy_definition::Neighboring;
+    let sensitivity = match get_neighboring(definition) {
+        Neighboring::Substitute => (maximum - minimum).powi(2) / num_records,
+        Neighboring::AddRemove => (maximum - minimum).powi(2) / num_records
+    };
+    scale_by_group(sensitivity, definition)
+}
+
+// sensitivity of the clamped sum of powers divided by num_records
+pub fn dp_moment_raw(definition: &yarrow::PrivacyDefinition, num_records: f64, minimum: f64, maximum: f64, order: u32) -> f64 {
+    use yarrow::privacy_definition::Neighboring;
+    let order = order as i32;
+    let largest = minimum.powi(order).max(maximum.powi(order));
+    let smallest = match order % 2 == 0 && minimum < 0. && maximum > 0. {
+        true => 0.,
+        false => minimum.powi(order).min(maximum.powi(order))
+    };
+    let sensitivity = match get_neighboring(definition) {
+        Neighboring::Substitute => (largest - smallest) / num_records,
+        Neighboring::AddRemove => largest.abs().max(smallest.abs()) / num_records
+    };
+    scale_by_group(sensitivity, definition)
+}
+
+// sensitivity of the clamped sum of co-deviations divided by num_records
+// Adding a record (x, y) to m - 1 others changes their sum of co-deviations by (m - 1) / m * (x - mean_x) * (y - mean_y),
+// where the means are of the other records, so the change is at most (m - 1) / m * range_x * range_y.
+// A substitution removes one such term and adds another, so the sum changes by at most 2 (m - 1) / m * range_x * range_y.
+// m is the private number of records actually present, so the bound uses 2 range_x * range_y, which holds for every m.
+pub fn dp_covariance(definition: &yarrow::PrivacyDefinition, num_records: f64,
+                     minimum_x: f64, minimum_y: f64,
+                     maximum_x: f64, maximum_y: f64) -> f64 {
+    use yarrow::privacy_definition::Neighboring;
+    let ranges = (maximum_x - minimum_x) * (maximum_y - minimum_y);
+    let sensitivity = match get_neighboring(definition) {
+        Neighboring::Substitute => 2. * ranges / num_records,
+        Neighboring::AddRemove => ranges / num_records
+    };
+    scale_by_group(sensitivity, definition)
+}
+
+// sensitivity of the utility of a candidate, which is a count of records
+pub fn exponential_mechanism(definition: &yarrow::PrivacyDefinition, utility: i32) -> f64 {
+    use yarrow::privacy_definition::Neighboring;
+    use yarrow::exponential_mechanism::Utility;
+    let sensitivity = match (Utility::from_i32(utility).unwrap_or(Utility::Count), get_neighboring(definition)) {
+        (Utility::Count, _) => 1.,
+        // a substituted record may move from below the candidate to above it
+        (Utility::Median, Neighboring::Substitute) => 2.,
+        (Utility::Median, Neighboring::AddRemove) => 1.
+    };
+    scale_by_group(sensitivity, definition)
+}
+
+// sensitivity of the statistic released by a privatizer, or None if its public arguments are unknown
+pub fn get_sensitivity(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release) -> Option<f64> {
+    use yarrow::component::Value::*;
+    let definition = analysis.privacy_definition.clone().unwrap_or_default();
+    let component = analysis.graph.get(&node_id)?;
+    let scalar = |argument: &str| base::get_argument_f64(node_id, argument, analysis, release);
+
+    Some(match component.value.as_ref()? {
+        Dpmean(_x) => dp_mean(&definition,
+            scalar("num_records")?, scalar("minimum")?, scalar("maximum")?),
+        Dpvariance(_x) => dp_variance(&definition,
+            scalar("num_records")?, scalar("minimum")?, scalar("maximum")?),
+        Dpmomentraw(x) => dp_moment_raw(&definition,
+            scalar("num_records")?, scalar("minimum")?, scalar("maximum")?, x.order),
+        Dpcovariance(_x) => dp_covariance(&definition,
+            scalar("num_records")?, scalar("minimum_x")?, scalar("minimum_y")?, scalar("maximum_x")?, scalar("maximum_y")?),
+        Exponentialmechanism(x) => exponential_mechanism(&definition, x.utility),
+        _ => return None
+    })
+}
+
+// how the output of a node changes between neighboring datasets
+#[derive(Clone, Debug, Default)]
+pub struct Propagation {
+    // number of output rows that may change when a group of records changes, for unaggregated data
+    pub stability: Option<f64>,
+    // sensitivity of the output, for aggregated data
+    pub sensitivity: Option<yarrow::Sensitivity>
+}
+
+fn scale(sensitivity: &yarrow::Sensitivity, factor: f64) -> yarrow::Sensitivity {
+    yarrow::Sensitivity {l1: sensitivity.l1 * factor.abs(), l2: sensitivity.l2 * factor.abs()}
+}
+
+fn add(left: &yarrow::Sensitivity, right: &yarrow::Sensitivity) -> yarrow::Sensitivity {
+    yarrow::Sensitivity {l1: left.l1 + right.l1, l2: left.l2 + right.l2}
+}
+
+fn scalar_sensitivity(sensitivity: f64) -> yarrow::Sensitivity {
+    yarrow::Sensitivity {l1: sensitivity, l2: sensitivity}
+}
+
+// propagate changes in the private data through one node, given the propagations of its arguments
+pub fn propagate(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release,
+                 properties: &HashMap<u32, properties::Properties>,
+                 propagations: &HashMap<u32, Propagation>) -> Propagation {
+    use yarrow::component::Value::*;
+    use yarrow::privacy_definition::Neighboring;
+
+    let definition = analysis.privacy_definition.clone().unwrap_or_default();
+    let component = match analysis.graph.get(&node_id) {
+        Some(component) => component,
+        None => return Propagation::default()
+    };
+
+    let source = |name: &str| component.arguments.get(name).map(|field| field.source_node_id);
+    let argument = |name: &str| source(name)
+        .and_then(|source_node_id| propagations.get(&source_node_id))
+        .cloned().unwrap_or_default();
+    let argument_properties = |name: &str| source(name)
+        .and_then(|source_node_id| properties.get(&source_node_id))
+        .cloned().unwrap_or_default();
+    let public_scalar = |name: &str| match argument_properties(name).private {
+        true => None,
+        false => base::get_argument_f64(node_id, name, analysis, release)
+    };
+
+    // changes in any private operand of an elementwise operation carry through
+    let elementwise = |names: &[&str]| {
+        let operands: Vec<Propagation> = names.iter()
+            .filter(|name| argument_properties(name).private)
+            .map(|name| argument(name)).collect();
+        if operands.is_empty() {
+            return Propagation::default();
+        }
+        Propagation {
+            stability: operands.iter().map(|propagation| propagation.stability)
+                .try_fold(0., |total, stability| Some(total + stability?)),
+            sensitivity: operands.iter().map(|propagation| propagation.sensitivity.clone())
+                .try_fold(scalar_sensitivity(0.), |total, sensitivity| Some(add(&total, &sensitivity?)))
+        }
+    };
+
+    let value = match component.value.as_ref() {
+        Some(value) => value,
+        None => return Propagation::default()
+    };
+
+    // released values no longer change with the private data
+    if !properties.get(&node_id).map(|node_properties| node_properties.private).unwrap_or(false)
+        && !privacy::is_privatizer(component) {
+        return Propagation::default();
+    }
+
+    let data = argument("data");
+    let data_properties = argument_properties("data");
+
+    match value {
+        Literal(_) | Constant(_) => Propagation::default(),
+
+        // the stability and sensitivity of a custom evaluation are unknown
+        Custom(_) => Propagation::default(),
+
+        // each record of the dataset is one row, and a group of records may change
+        Datasource(_x) => Propagation {
+            stability: Some(f64::from(privacy::get_group_size(&definition))),
+            sensitivity: None
+        },
+
+        // binning is 1-stable
+        Clip(_) | Bin(_) | Negate(_) | Laplace(_) => data,
+
+        Add(_) | Subtract(_) => elementwise(&["left", "right"]),
+        // raising to a power may amplify a change by far more than the sensitivities of the operands,
+        // so only the stability carries through
+        Power(_) => Propagation {sensitivity: None, ..elementwise(&["left", "right"])},
+
+        // scaling by a public constant scales the sensitivity
+        Multiply(_) | Divide(_) => {
+            let divide = matches!(value, Divide(_));
+            let (scaled, factor) = match (public_scalar("left"), public_scalar("right")) {
+                (_, Some(right)) => (argument("left"), if divide {1. / right} else {right}),
+                (Some(left), None) if !divide => (argument("right"), left),
+                _ => return Propagation {sensitivity: None, ..elementwise(&["left", "right"])}
+            };
+            match factor.is_finite() {
+                true => Propagation {
+                    sensitivity: scaled.sensitivity.as_ref().map(|sensitivity| scale(sensitivity, factor)),
+                    ..scaled
+                },
+                false => Propagation {sensitivity: None, ..scaled}
+            }
+        },
+
+        Sum(_x) => Propagation {
+            stability: None,
+            sensitivity: data.stability.and_then(|stability| data_properties.bounds
+                .map(|(minimum, maximum)| scalar_sensitivity(stability * match get_neighboring(&definition) {
+                    Neighboring::Substitute => maximum - minimum,
+                    Neighboring::AddRemove => minimum.abs().max(maximum.abs())
+                })))
+        },
+
+        // moving a record between groups changes two counts, while adding or removing changes one
+        Count(_x) => Propagation {
+            stability: None,
+            sensitivity: data.stability.map(|stability| match get_neighboring(&definition) {
+                Neighboring::Substitute => yarrow::Sensitivity {l1: 2. * stability, l2: 2_f64.sqrt() * stability},
+                Neighboring::AddRemove => scalar_sensitivity(stability)
+            })
+        },
+
+        // the median always lies within the bounds of the data
+        Median(_x) => Propagation {
+            stability: None,
+            sensitivity: data_properties.bounds.map(|(minimum, maximum)| scalar_sensitivity(maximum - minimum))
+        },
+
+        // the mean of a public number of records in a bounded interval
+        Mean(_x) => Propagation {
+            stability: None,
+            sensitivity: match (data.stability, data_properties.bounds, data_properties.num_records) {
+                (Some(stability), Some((minimum, maximum)), Some(num_records)) if num_records > 0 =>
+                    Some(scalar_sensitivity(stability * (maximum - minimum) / num_records as f64)),
+                _ => None
+            }
+        },
+
+        Dpmean(_) | Dpvariance(_) | Dpmomentraw(_) | Dpcovariance(_) | Exponentialmechanism(_) => Propagation {
+            stability: None,
+            sensitivity: get_sensitivity(node_id, analysis, release).map(scalar_sensitivity)
+        }
+    }
+}
+
+pub fn propagate_analysis(analysis: &yarrow::Analysis, release: &yarrow::Release) -> HashMap<u32, Propagation> {
+    let properties = properties::infer_properties(analysis, release);
+    let mut propagations = HashMap::<u32, Propagation>::new();
+    // an invalid graph has no well-defined propagation
+    if let Ok(traversal) = base::get_traversal(analysis) {
+        for node_id in traversal {
+            let propagation = propagate(node_id, analysis, release, &properties, &propagations);
+            propagations.insert(node_id, propagation);
+        }
+    }
+    propagations
+}
+
+pub fn compute_sensitivities(analysis: &yarrow::Analysis, release: &yarrow::Release) -> yarrow::Sensitivities {
+    let values: HashMap<u32, yarrow::Sensitivity> = propagate_analysis(analysis, release).into_iter()
+        .filter_map(|(node_id, propagation)| propagation.sensitivity
+            .map(|sensitivity| (node_id, sensitivity)))
+        .collect();
+    yarrow::Sensitivities {values}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    fn definition(neighboring: yarrow::privacy_definition::Neighboring) -> yarrow::PrivacyDefinition {
+        yarrow::PrivacyDefinition {neighboring: neighboring as i32, group_size: 1, ..Default::default()}
+    }
+
+    // covariance of the data, dividing the sum of co-deviations by num_records
+    fn covariance(data: &[(f64, f64)], num_records: f64) -> f64 {
+        let mean_x = data.iter().map(|(x, _)| x).sum::<f64>() / data.len() as f64;
+        let mean_y = data.iter().map(|(_, y)| y).sum::<f64>() / data.len() as f64;
+        data.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / num_records
+    }
+
+    // largest change in the statistic when one of the length records is substituted,
+    // over datasets drawn from the corners of the bounds
+    fn largest_substitution(corners: &[(f64, f64)], length: usize, num_records: f64) -> f64 {
+        let mut largest: f64 = 0.;
+        for index in 0..corners.len().pow(length as u32) {
+            let data: Vec<(f64, f64)> = (0..length)
+                .map(|i| corners[index / corners.len().pow(i as u32) % corners.len()]).collect();
+            for substitute in corners {
+                let mut neighbor = data.clone();
+                neighbor[0] = *substitute;
+                largest = largest.max((covariance(&data, num_records) - covariance(&neighbor, num_records)).abs());
+            }
+        }
+        largest
+    }
+
+    #[test]
+    fn dp_covariance_pinned() {
+        use yarrow::privacy_definition::Neighboring;
+        let substitute = dp_covariance(&definition(Neighboring::Substitute), 10., 0., 0., 1., 2.);
+        assert!((substitute - 2. * 2. / 10.).abs() < 1e-12);
+        let add_remove = dp_covariance(&definition(Neighboring::AddRemove), 10., 0., 0., 1., 2.);
+        assert!((add_remove - 2. / 10.).abs() < 1e-12);
+        let variance = dp_variance(&definition(Neighboring::Substitute), 10., 0., 2.);
+        assert!((variance - 4. / 10.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn dp_covariance_bounds_substitution() {
+        use yarrow::privacy_definition::Neighboring;
+        let corners = [(0., 0.), (0., 2.), (1., 0.), (1., 2.)];
+        let sensitivity = dp_covariance(&definition(Neighboring::Substitute), 4., 0., 0., 1., 2.);
+
+        // the covariance is bilinear in the records, so the largest change is between datasets on the corners of the bounds
+        let largest = largest_substitution(&corners, 4, 4.);
+        assert!(largest <= sensitivity + 1e-12);
+        // the largest change is (m - 1) / 2m of the bound, which holds for every m
+        assert!(largest >= sensitivity * 3. / 8. - 1e-12);
+    }
+
+    #[test]
+    fn sensitivities_hold_when_records_differ_from_num_records() {
+        use yarrow::privacy_definition::Neighboring;
+        let num_records = 3.;
+        for length in &[2, 5] {
+            // more or fewer records are present than the public num_records
+            let covariance_sensitivity = dp_covariance(&definition(Neighboring::Substitute), num_records, 0., 0., 1., 2.);
+            let largest = largest_substitution(&[(0., 0.), (0., 2.), (1., 0.), (1., 2.)], *length, num_records);
+            assert!(largest <= covariance_sensitivity + 1e-12, "{} records", length);
+
+            // the variance is the covariance of the data with itself
+            let variance_sensitivity = dp_variance(&definition(Neighboring::Substitute), num_records, 0., 2.);
+            let largest = largest_substitution(&[(0., 0.), (2., 2.)], *length, num_records);
+            assert!(largest <= variance_sensitivity + 1e-12, "{} records", length);
+        }
+    }
+
+    #[test]
+    fn power_does_not_add_sensitivities() {
+        let analysis = yarrow::Analysis {
+            privacy_definition: Some(definition(yarrow::privacy_definition::Neighboring::Substitute)),
+            ..analysis_from(vec![
+                (1, datasource(yarrow::DataType::F64)),
+                (2, literal(0.)), (3, literal(10.)),
+                (4, component(Clip(yarrow::Clip::default()), &[("data", 1), ("minimum", 2), ("maximum", 3)])),
+                (5, component(Sum(yarrow::Sum::default()), &[("data", 4)])),
+                (6, literal(2.)),
+                (7, component(Power(yarrow::Power::default()), &[("left", 5), ("right", 6)])),
+                (8, component(Add(yarrow::Add::default()), &[("left", 5), ("right", 5)])),
+                (9, component(Power(yarrow::Power::default()), &[("left", 4), ("right", 6)]))])
+        };
+        let propagations = propagate_analysis(&analysis, &yarrow::Release::default());
+
+        assert_eq!(propagations[&5].sensitivity.as_ref().map(|sensitivity| sensitivity.l1), Some(10.));
+        assert_eq!(propagations[&8].sensitivity.as_ref().map(|sensitivity| sensitivity.l1), Some(20.));
+        // the square of the sum may change by up to 10^2, which no sum of the operand sensitivities bounds
+        assert!(propagations[&7].sensitivity.is_none());
+        // each row of the powered data still depends on one record
+        assert_eq!(propagations[&9].stability, Some(1.));
+        assert!(propagations[&9].sensitivity.is_none());
+    }
+}
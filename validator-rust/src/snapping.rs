@@ -0,0 +1,102 @@
+use crate::yarrow;
+use crate::base;
+
+// The snapping mechanism adds Laplace noise in floating point, then rounds to a power of two and clamps to a bound B,
+// so that the release does not leak through the gaps between floating-point numbers.
+// https://github.com/ctcovington/floating_point/blob/master/snapping_mechanism/notes/snapping_implementation_notes.pdf
+// Bounds passed to these functions are relative to the sensitivity, as the mechanism scales its input by the sensitivity.
+
+// bits of arithmetic precision the runtime samples with
+pub const PRECISION: f64 = 118.;
+
+// epsilon that determines the noise, which is smaller than epsilon to cover the error of floating-point arithmetic
+pub fn redefine_epsilon(epsilon: f64, bound: f64, precision: f64) -> f64 {
+    let eta = 2_f64.powf(-precision);
+    (epsilon - 2. * eta) / (1. + 12. * bound * eta)
+}
+
+// effective epsilon of the noise, for a bound on the statistic in its own units
+pub fn get_epsilon_prime(epsilon: f64, bound: f64, sensitivity: f64) -> f64 {
+    redefine_epsilon(epsilon, bound / sensitivity, PRECISION)
+}
+
+// half-width of the (1 - alpha) interval of the noise
+pub fn get_accuracy(alpha: f64, epsilon: f64, sensitivity: f64, bound: f64, precision: f64) -> f64 {
+    ((1. + 12. * bound * 2_f64.powf(-precision)) / (epsilon - 2_f64.powf(-precision + 1.)))
+        * (1. + (1. / alpha).ln()) * sensitivity
+}
+
+// smallest epsilon for which the noise is within the accuracy, the inverse of get_accuracy
+pub fn get_epsilon(accuracy: f64, alpha: f64, sensitivity: f64, bound: f64, precision: f64) -> f64 {
+    ((1. + 12. * bound * 2_f64.powf(-precision)) / accuracy) * (1. + (1. / alpha).ln())
+        * sensitivity + 2_f64.powf(-precision + 1.)
+}
+
+// bounds on the magnitude of each statistic, given the clamping bounds of its data
+
+pub fn dp_mean_bound(minimum: f64, maximum: f64) -> f64 {
+    minimum.abs().max(maximum.abs())
+}
+
+pub fn dp_variance_bound(minimum: f64, maximum: f64) -> f64 {
+    (maximum - minimum).powi(2)
+}
+
+pub fn dp_moment_raw_bound(minimum: f64, maximum: f64, order: u32) -> f64 {
+    minimum.abs().max(maximum.abs()).powi(order as i32)
+}
+
+pub fn dp_covariance_bound(minimum_x: f64, minimum_y: f64, maximum_x: f64, maximum_y: f64) -> f64 {
+    (maximum_x - minimum_x) * (maximum_y - minimum_y)
+}
+
+// bound on the statistic released by a privatizer, or None if its public arguments are unknown
+pub fn get_bound(node_id: u32, analysis: &yarrow::Analysis, release: &yarrow::Release) -> Option<f64> {
+    use yarrow::component::Value::*;
+    let component = analysis.graph.get(&node_id)?;
+    let scalar = |argument: &str| base::get_argument_f64(node_id, argument, analysis, release);
+
+    Some(match component.value.as_ref()? {
+        Dpmean(_) => dp_mean_bound(scalar("minimum")?, scalar("maximum")?),
+        Dpvariance(_) => dp_variance_bound(scalar("minimum")?, scalar("maximum")?),
+        Dpmomentraw(x) => dp_moment_raw_bound(scalar("minimum")?, scalar("maximum")?, x.order),
+        Dpcovariance(_) => dp_covariance_bound(
+            scalar("minimum_x")?, scalar("minimum_y")?, scalar("maximum_x")?, scalar("maximum_y")?),
+        _ => return None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::*;
+    use yarrow::component::Value::*;
+
+    #[test]
+    fn epsilon_covers_floating_point_error() {
+        assert_eq!(get_epsilon_prime(1., 10., 0.01), redefine_epsilon(1., 1000., PRECISION));
+        assert!(get_epsilon_prime(1., 10., 0.01) <= 1.);
+        // the error is only visible in double precision when the precision is low
+        let epsilon_prime = redefine_epsilon(1., 1000., 32.);
+        assert!(epsilon_prime < 1. && epsilon_prime > 1. - 1e-5);
+    }
+
+    #[test]
+    fn epsilon_inverts_accuracy() {
+        let accuracy = get_accuracy(0.05, 0.5, 0.1, 100., PRECISION);
+        assert!((get_epsilon(accuracy, 0.05, 0.1, 100., PRECISION) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn bound_of_privatizer() {
+        let analysis = analysis_from(vec![
+            (1, datasource(yarrow::DataType::F64)),
+            (2, literal(-20.)), (3, literal(10.)),
+            (4, component(Dpmean(yarrow::DpMean::default()), &[("data", 1), ("minimum", 2), ("maximum", 3)])),
+            (5, component(Dpvariance(yarrow::DpVariance::default()), &[("data", 1), ("minimum", 2), ("maximum", 3)]))]);
+
+        assert_eq!(get_bound(4, &analysis, &yarrow::Release::default()), Some(20.));
+        assert_eq!(get_bound(5, &analysis, &yarrow::Release::default()), Some(900.));
+        assert_eq!(get_bound(1, &analysis, &yarrow::Release::default()), None);
+    }
+}